@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use zip::write::FileOptions;
+use crate::error::InstallerError;
+use crate::extract::Extractor;
+use crate::transaction::Transaction;
+
+// everything below the game folder that belongs to the mod setup, the records describe the files next to them
+const SNAPSHOT_PATHS: [&str; 21] = [
+    "BepInEx/installer.json",
+    "BepInEx/plugins",
+    "BepInEx/disabled",
+    "BepInEx/config",
+    "BepInEx/core",
+    "dotnet",
+    "winhttp.dll",
+    "winhttp.dll.disabled",
+    "doorstop_config.ini",
+    ".doorstop_version",
+    "Mods",
+    "MelonLoader/Disabled",
+    "Plugins",
+    "UserData",
+    "UserLibs",
+    "MelonLoader/net6",
+    "MelonLoader/net35",
+    "MelonLoader/Dependencies",
+    "version.dll",
+    "version.dll.disabled",
+    "dobby.dll",
+];
+
+pub struct Backup {
+    path: String,
+}
+
+#[derive(Serialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+#[derive(Serialize)]
+pub struct RestoreResult {
+    pub restored: usize,
+    pub removed: usize,
+}
+
+impl Backup {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    fn backups_path(&self) -> PathBuf {
+        return Path::new(&self.path).join("BepInEx-backups");
+    }
+
+    pub fn create(&self) -> Result<Snapshot, InstallerError> {
+        return self.create_with(&[]);
+    }
+
+    /// Like `create`, with the files below `extra` on top of the mod setup.
+    pub fn create_with(&self, extra: &[&str]) -> Result<Snapshot, InstallerError> {
+        let game_path = Path::new(&self.path);
+        let backups_path = self.backups_path();
+        std::fs::create_dir_all(&backups_path).map_err(InstallerError::io(&backups_path))?;
+
+        // a second snapshot in the same second gets a counter
+        let timestamp = Backup::timestamp();
+        let mut name = format!("snapshot-{}", timestamp);
+        let mut counter = 1;
+        while backups_path.join(format!("{}.zip", name)).exists() {
+            counter += 1;
+            name = format!("snapshot-{}-{}", timestamp, counter);
+        }
+        let snapshot_path = backups_path.join(format!("{}.zip", name));
+
+        // collect every file of the mod setup
+        let mut files = Vec::new();
+        for entry in SNAPSHOT_PATHS.iter().chain(extra) {
+            Transaction::collect_files(game_path, Path::new(entry), &mut files).map_err(InstallerError::io(game_path.join(entry)))?;
+        }
+        files.sort();
+        files.dedup();
+
+        // a half written snapshot would show up as restorable
+        if let Err(err) = Backup::write_archive(game_path, &snapshot_path, &files) {
+            let _ = std::fs::remove_file(&snapshot_path);
+            return Err(err);
+        }
+
+        let size = std::fs::metadata(&snapshot_path).map_err(InstallerError::io(&snapshot_path))?.len();
+
+        return Ok(Snapshot {
+            name,
+            path: snapshot_path,
+            size,
+        });
+    }
+
+    // writes the files into the archive with forward slashes so they extract on every platform
+    fn write_archive(game_path: &Path, snapshot_path: &Path, files: &[PathBuf]) -> Result<(), InstallerError> {
+        // `create_new` never replaces another snapshot
+        let file = std::fs::OpenOptions::new().write(true).create_new(true).open(snapshot_path).map_err(InstallerError::io(snapshot_path))?;
+        let mut archive = zip::ZipWriter::new(file);
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for relative in files {
+            let name = Backup::entry_name(relative);
+            archive.start_file(name, options).map_err(InstallerError::zip(snapshot_path))?;
+            let contents = std::fs::read(game_path.join(relative)).map_err(InstallerError::io(game_path.join(relative)))?;
+            archive.write_all(&contents).map_err(InstallerError::io(snapshot_path))?;
+        }
+        archive.finish().map_err(InstallerError::zip(snapshot_path))?;
+
+        return Ok(());
+    }
+
+    pub fn list(&self) -> Result<Vec<Snapshot>, InstallerError> {
+        let backups_path = self.backups_path();
+        let mut snapshots = Vec::new();
+        if !backups_path.exists() {
+            return Ok(snapshots);
+        }
+
+        for entry in std::fs::read_dir(&backups_path).map_err(InstallerError::io(&backups_path))? {
+            let entry = entry.map_err(InstallerError::io(&backups_path))?;
+            let path = entry.path();
+            if path.extension().and_then(|x| x.to_str()) != Some("zip") {
+                continue;
+            }
+            let name = path.file_stem().and_then(|x| x.to_str()).unwrap_or("").to_string();
+            let size = entry.metadata().map_err(InstallerError::io(&path))?.len();
+            snapshots.push(Snapshot {
+                name,
+                path,
+                size,
+            });
+        }
+
+        // timestamps sort chronologically
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+
+        return Ok(snapshots);
+    }
+
+    /// Restores a snapshot exactly, files added since the snapshot was taken are deleted.
+    pub fn restore(&self, name: &str) -> Result<RestoreResult, InstallerError> {
+        let name = name.trim_end_matches(".zip");
+        let snapshot = match self.list()?.into_iter().find(|x| x.name == name) {
+            Some(snapshot) => snapshot,
+            None => return Err(InstallerError::ValidationError(format!("Snapshot {} not found", name))),
+        };
+
+        let game_path = Path::new(&self.path);
+        let temp_path = game_path.join("temp");
+        let mut transaction = Transaction::new(game_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+
+        // stage the snapshot contents
+        let archive = std::fs::File::open(&snapshot.path).map_err(InstallerError::io(&snapshot.path))?;
+        let mut archive = zip::ZipArchive::new(archive).map_err(InstallerError::zip(&snapshot.path))?;
+        let extracted = Extractor::extract(&mut archive, &transaction.staging_path()).map_err(InstallerError::zip(&snapshot.path))?;
+        if !extracted.rejected.is_empty() {
+            return Err(InstallerError::ValidationError(format!("Snapshot {} contains unsafe entries", name)));
+        }
+        let restored = extracted.extracted.len();
+
+        // delete every file that is not part of the snapshot
+        let snapshot_files = archive.file_names()
+            .map(|x| x.replace('\\', "/"))
+            .collect::<HashSet<_>>();
+        let mut current_files = Vec::new();
+        for entry in SNAPSHOT_PATHS {
+            Transaction::collect_files(game_path, Path::new(entry), &mut current_files).map_err(InstallerError::io(game_path.join(entry)))?;
+        }
+        // snapshots from before the records were part of them keep the current records
+        let records_name = SNAPSHOT_PATHS[0];
+        let keep_records = !snapshot_files.contains(records_name);
+        let mut removed = 0;
+        for relative in current_files {
+            let name = Backup::entry_name(&relative);
+            let kept = snapshot_files.contains(&name) || (keep_records && name == records_name);
+            if !kept {
+                transaction.remove(&relative);
+                removed += 1;
+            }
+        }
+
+        transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        return Ok(RestoreResult {
+            restored,
+            removed,
+        });
+    }
+
+    // the files in a snapshot, relative to the game folder with forward slashes
+    pub(crate) fn entries(snapshot: &Snapshot) -> Result<HashSet<String>, InstallerError> {
+        let archive = std::fs::File::open(&snapshot.path).map_err(InstallerError::io(&snapshot.path))?;
+        let archive = zip::ZipArchive::new(archive).map_err(InstallerError::zip(&snapshot.path))?;
+
+        return Ok(archive.file_names().map(|x| x.replace('\\', "/")).collect());
+    }
+
+    pub(crate) fn entry_name(relative: &Path) -> String {
+        return relative
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+    }
+
+    // current utc time as YYYYMMDD-HHMMSS
+    pub(crate) fn timestamp() -> String {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0);
+        let (year, month, day, time) = Backup::civil_date(seconds);
+
+        return format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, time / 3600, time % 3600 / 60, time % 60);
+    }
+
+    // year, month, day and seconds into the day of a unix time, in utc
+    pub(crate) fn civil_date(seconds: u64) -> (i64, i64, i64, u64) {
+        let days = (seconds / 86400) as i64;
+        let time = seconds % 86400;
+
+        // days since epoch to civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        return (year, month, day, time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_puts_back_the_snapshot_exactly() {
+        let root = std::env::temp_dir().join(format!("bepinex_installer-backup-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("BepInEx/plugins")).unwrap();
+        std::fs::write(root.join("BepInEx/plugins/Kept.dll"), "original").unwrap();
+        std::fs::write(root.join("BepInEx/installer.json"), "{\"plugins\": [\"Kept\"]}").unwrap();
+        let path = root.to_string_lossy().to_string();
+        let backup = Backup::new(&path);
+
+        // a second snapshot in the same second never replaces the first one
+        let snapshot = backup.create().unwrap();
+        let second = backup.create().unwrap();
+        assert_ne!(snapshot.name, second.name);
+        assert_eq!(backup.list().unwrap().len(), 2);
+
+        std::fs::write(root.join("BepInEx/plugins/Kept.dll"), "changed").unwrap();
+        std::fs::write(root.join("BepInEx/plugins/Added.dll"), "added").unwrap();
+        std::fs::write(root.join("BepInEx/installer.json"), "{\"plugins\": [\"Kept\", \"Added\"]}").unwrap();
+        let result = backup.restore(&snapshot.name).unwrap();
+
+        assert_eq!(result.restored, 2);
+        assert_eq!(result.removed, 1);
+        assert_eq!(std::fs::read_to_string(root.join("BepInEx/plugins/Kept.dll")).unwrap(), "original");
+        assert_eq!(std::fs::read_to_string(root.join("BepInEx/installer.json")).unwrap(), "{\"plugins\": [\"Kept\"]}");
+        assert!(!root.join("BepInEx/plugins/Added.dll").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,351 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::error::InstallerError;
+
+/// A BepInEx `.cfg` file. Only the values that are set get rewritten, every other line,
+/// comment and blank line stays exactly as it was so the file keeps its layout.
+pub struct CfgFile {
+    lines: Vec<String>,
+    entries: Vec<CfgEntry>,
+    line_ending: &'static str,
+    trailing_newline: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CfgEntry {
+    pub section: String,
+    pub key: String,
+    pub value: String,
+    // the `## ` lines above the setting
+    pub description: Vec<String>,
+    pub setting_type: Option<String>,
+    pub default: Option<String>,
+    pub acceptable: Option<Acceptable>,
+    #[serde(skip)]
+    line: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Acceptable {
+    // enums take one of them
+    Values(Vec<String>),
+    // flag enums take several of them separated by commas
+    Flags(Vec<String>),
+    Range(String, String),
+}
+
+impl CfgFile {
+    pub fn parse(contents: &str) -> Self {
+        let line_ending = if contents.contains("\r\n") { "\r\n" } else { "\n" };
+        let lines = contents.lines().map(|x| x.to_string()).collect::<Vec<_>>();
+
+        let mut entries = Vec::new();
+        let mut section = String::new();
+        let mut description = Vec::new();
+        let mut setting_type = None;
+        let mut default = None;
+        let mut acceptable = None;
+        let mut flags = false;
+        for (index, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                // comments above a section header do not belong to the first setting in it
+                section = trimmed[1..trimmed.len() - 1].trim().to_string();
+                description.clear();
+                setting_type = None;
+                default = None;
+                acceptable = None;
+                flags = false;
+                continue;
+            }
+            if let Some(text) = trimmed.strip_prefix("##") {
+                description.push(text.trim().to_string());
+                continue;
+            }
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                let comment = comment.trim();
+                if let Some(value) = comment.strip_prefix("Setting type:") {
+                    setting_type = Some(value.trim().to_string());
+                } else if let Some(value) = comment.strip_prefix("Default value:") {
+                    default = Some(value.trim().to_string());
+                } else if let Some(value) = comment.strip_prefix("Acceptable values:") {
+                    acceptable = Some(Acceptable::Values(value.split(',').map(|x| x.trim().to_string()).collect()));
+                } else if comment.starts_with("Multiple values can be set at the same time") {
+                    // written below the acceptable values of `[Flags]` enums
+                    flags = true;
+                } else if let Some(value) = comment.strip_prefix("Acceptable value range:") {
+                    // "From 0 to 100"
+                    let value = value.trim().trim_start_matches("From").trim();
+                    if let Some((from, to)) = value.split_once(" to ") {
+                        acceptable = Some(Acceptable::Range(from.trim().to_string(), to.trim().to_string()));
+                    }
+                }
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                if let (true, Some(Acceptable::Values(values))) = (std::mem::take(&mut flags), &acceptable) {
+                    acceptable = Some(Acceptable::Flags(values.clone()));
+                }
+                entries.push(CfgEntry {
+                    section: section.clone(),
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                    description: std::mem::take(&mut description),
+                    setting_type: setting_type.take(),
+                    default: default.take(),
+                    acceptable: acceptable.take(),
+                    line: index,
+                });
+            }
+        }
+
+        return Self {
+            lines,
+            entries,
+            line_ending,
+            trailing_newline: contents.ends_with('\n'),
+        };
+    }
+
+    pub fn load(path: &Path) -> Result<Self, InstallerError> {
+        let contents = std::fs::read_to_string(path).map_err(InstallerError::io(path))?;
+        return Ok(CfgFile::parse(&contents));
+    }
+
+    // written next to the file first so a failed write never leaves half a config behind
+    pub fn save(&self, path: &Path) -> Result<(), InstallerError> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        std::fs::write(&temp_path, self.to_string()).map_err(InstallerError::io(&temp_path))?;
+
+        return std::fs::rename(&temp_path, path).map_err(InstallerError::io(path));
+    }
+
+    pub fn entries(&self) -> &[CfgEntry] {
+        return &self.entries;
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&CfgEntry> {
+        return self.entries.iter().find(|x| x.section.eq_ignore_ascii_case(section) && x.key.eq_ignore_ascii_case(key));
+    }
+
+    /// Sets a value after checking it against the type and acceptable values of the setting.
+    pub fn set(&mut self, section: &str, key: &str, value: &str) -> Result<(), InstallerError> {
+        let index = match self.entries.iter().position(|x| x.section.eq_ignore_ascii_case(section) && x.key.eq_ignore_ascii_case(key)) {
+            Some(index) => index,
+            None => return Err(InstallerError::ValidationError(format!("[{}] {} does not exist", section, key))),
+        };
+        let value = value.trim();
+        CfgFile::validate(&self.entries[index], value)?;
+
+        let entry = &mut self.entries[index];
+        entry.value = value.to_string();
+        self.lines[entry.line] = format!("{} = {}", entry.key, value);
+
+        return Ok(());
+    }
+
+    /// Puts a setting back to its default value.
+    pub fn reset(&mut self, section: &str, key: &str) -> Result<(), InstallerError> {
+        let default = match self.get(section, key) {
+            Some(entry) => entry.default.clone(),
+            None => return Err(InstallerError::ValidationError(format!("[{}] {} does not exist", section, key))),
+        };
+
+        return match default {
+            Some(default) => self.set(section, key, &default),
+            None => Err(InstallerError::ValidationError(format!("[{}] {} has no default value", section, key))),
+        };
+    }
+
+    /// Puts every setting with a default back to it, returns the settings that changed.
+    pub fn reset_all(&mut self) -> Vec<CfgEntry> {
+        let mut changed = Vec::new();
+        for index in 0..self.entries.len() {
+            let entry = &self.entries[index];
+            if let Some(default) = entry.default.clone().filter(|x| *x != entry.value) {
+                let (section, key) = (entry.section.clone(), entry.key.clone());
+                if self.set(&section, &key, &default).is_ok() {
+                    changed.push(self.entries[index].clone());
+                }
+            }
+        }
+
+        return changed;
+    }
+
+    fn validate(entry: &CfgEntry, value: &str) -> Result<(), InstallerError> {
+        let invalid = |reason: String| Err(InstallerError::ValidationError(format!("[{}] {}: {}", entry.section, entry.key, reason)));
+        if value.contains('\n') || value.contains('\r') {
+            return invalid("values have to fit on one line".to_string());
+        }
+
+        match entry.setting_type.as_deref() {
+            Some("Boolean") if !value.eq_ignore_ascii_case("true") && !value.eq_ignore_ascii_case("false") => {
+                return invalid(format!("{} is not true or false", value));
+            }
+            Some("Int32" | "Int64" | "Byte" | "SByte" | "Int16" | "UInt16" | "UInt32" | "UInt64") if value.parse::<i128>().is_err() => {
+                return invalid(format!("{} is not a whole number", value));
+            }
+            Some("Single" | "Double" | "Decimal") if value.parse::<f64>().is_err() => return invalid(format!("{} is not a number", value)),
+            _ => {}
+        }
+
+        match &entry.acceptable {
+            Some(Acceptable::Values(values)) if !values.iter().any(|x| x == value) => {
+                return invalid(format!("{} is not one of {}", value, values.join(", ")));
+            }
+            Some(Acceptable::Flags(values)) => {
+                for item in value.split(',').map(|x| x.trim()) {
+                    if !values.iter().any(|x| x == item) {
+                        return invalid(format!("{} is not one of {}", item, values.join(", ")));
+                    }
+                }
+            }
+            Some(Acceptable::Range(from, to)) => {
+                let (number, from, to) = (value.parse::<f64>(), from.parse::<f64>(), to.parse::<f64>());
+                if let (Ok(number), Ok(from), Ok(to)) = (number, from, to) {
+                    if number < from || number > to {
+                        return invalid(format!("{} is not between {} and {}", value, from, to));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        return Ok(());
+    }
+
+    /// The `.cfg` files in the config folder of the loader, by file name.
+    pub fn list(config_path: &Path) -> Result<Vec<String>, InstallerError> {
+        let mut files = Vec::new();
+        if !config_path.exists() {
+            return Ok(files);
+        }
+
+        for entry in std::fs::read_dir(config_path).map_err(InstallerError::io(config_path))? {
+            let entry = entry.map_err(InstallerError::io(config_path))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.to_lowercase().ends_with(".cfg") {
+                files.push(name);
+            }
+        }
+        files.sort_by_key(|x| x.to_lowercase());
+
+        return Ok(files);
+    }
+
+    /// Finds a config file by name, the `.cfg` extension and the case do not matter.
+    pub fn find(config_path: &Path, name: &str) -> Result<PathBuf, InstallerError> {
+        let wanted = name.trim_end_matches(".cfg").to_lowercase();
+        let file = CfgFile::list(config_path)?
+            .into_iter()
+            .find(|x| x.to_lowercase().trim_end_matches(".cfg") == wanted);
+
+        return match file {
+            Some(file) => Ok(config_path.join(file)),
+            None => Err(InstallerError::ValidationError(format!("{}.cfg does not exist, start the game once to create it", wanted))),
+        };
+    }
+}
+
+impl std::fmt::Display for CfgFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.lines.join(self.line_ending))?;
+        if self.trailing_newline {
+            write!(f, "{}", self.line_ending)?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "## Settings file was created by plugin BepInEx v5.4.22\r
+## Plugin GUID: BepInEx\r
+\r
+[Caching]\r
+\r
+## Enable/disable assembly metadata cache\r
+# Setting type: Boolean\r
+# Default value: true\r
+EnableAssemblyCache = true\r
+\r
+[Logging.Console]\r
+\r
+## Which log levels to show in the console output.\r
+# Setting type: LogLevel\r
+# Default value: Fatal, Error, Warning, Message, Info\r
+# Acceptable values: None, Fatal, Error, Warning, Message, Info, Debug, All\r
+# Multiple values can be set at the same time by separating them with , (e.g. Debug, Warning)\r
+LogLevels = Fatal, Error, Warning, Message, Info\r
+\r
+## Stream the console output goes to\r
+# Setting type: ConsoleOutRedirectType\r
+# Default value: Auto\r
+# Acceptable values: Auto, ConsoleOut, StandardOut\r
+StandardOutType = Auto\r
+\r
+## Orphaned comment\r
+# Setting type: Int32\r
+# Acceptable value range: From 0 to 10\r
+[Preloader]\r
+\r
+Enabled = false";
+
+    #[test]
+    fn parses_comments_and_sections() {
+        let file = CfgFile::parse(SAMPLE);
+        let cache = file.get("caching", "enableassemblycache").unwrap();
+        assert_eq!(cache.section, "Caching");
+        assert_eq!(cache.value, "true");
+        assert_eq!(cache.description, vec!["Enable/disable assembly metadata cache"]);
+        assert_eq!(cache.setting_type.as_deref(), Some("Boolean"));
+        assert_eq!(cache.default.as_deref(), Some("true"));
+
+        // the comments above the header do not belong to the first setting of the section
+        let enabled = file.get("Preloader", "Enabled").unwrap();
+        assert!(enabled.description.is_empty());
+        assert!(enabled.setting_type.is_none());
+        assert!(enabled.acceptable.is_none());
+    }
+
+    #[test]
+    fn only_flag_enums_take_several_values() {
+        let mut file = CfgFile::parse(SAMPLE);
+        assert!(matches!(file.get("Logging.Console", "LogLevels").unwrap().acceptable, Some(Acceptable::Flags(_))));
+        assert!(file.set("Logging.Console", "LogLevels", "Debug, Warning").is_ok());
+        assert!(file.set("Logging.Console", "LogLevels", "Debug, Loud").is_err());
+
+        assert!(matches!(file.get("Logging.Console", "StandardOutType").unwrap().acceptable, Some(Acceptable::Values(_))));
+        assert!(file.set("Logging.Console", "StandardOutType", "ConsoleOut").is_ok());
+        assert!(file.set("Logging.Console", "StandardOutType", "ConsoleOut, StandardOut").is_err());
+    }
+
+    #[test]
+    fn booleans_ignore_case() {
+        let mut file = CfgFile::parse(SAMPLE);
+        assert!(file.set("Caching", "EnableAssemblyCache", "False").is_ok());
+        assert!(file.set("Caching", "EnableAssemblyCache", "TRUE").is_ok());
+        assert!(file.set("Caching", "EnableAssemblyCache", "yes").is_err());
+    }
+
+    #[test]
+    fn round_trip_keeps_the_layout() {
+        let mut file = CfgFile::parse(SAMPLE);
+        assert_eq!(file.to_string(), SAMPLE);
+
+        file.set("Caching", "EnableAssemblyCache", "false").unwrap();
+        let expected = SAMPLE.replace("EnableAssemblyCache = true", "EnableAssemblyCache = false");
+        assert_eq!(file.to_string(), expected);
+
+        let unix = "[A]\n# Setting type: Int32\nValue = 1\n";
+        assert_eq!(CfgFile::parse(unix).to_string(), unix);
+    }
+}
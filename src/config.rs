@@ -2,6 +2,19 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
 pub const LICENSE: &str = "GNU General Public License v3.0";
 pub const TITLE: &str = "Soulstone Survivors BepInEx Installer";
+// steam app id of Soulstone Survivors
+pub const GAME_ID: &str = "2066020";
+pub const BEPINEX_VERSION: &str = "6.0.0-be.668";
 pub const BEPINEX_URL: &str = "https://builds.bepinex.dev/projects/bepinex_be/668/BepInEx-Unity.IL2CPP-win-x64-6.0.0-be.668%2B46e297f.zip";
+pub const MELONLOADER_VERSION: &str = "0.6.1";
+pub const MELONLOADER_URL: &str = "https://github.com/LavaGang/MelonLoader/releases/download/v0.6.1/MelonLoader.x64.zip";
+// how long the first launch may take, generating the interop assemblies is slow
+pub const FIRST_RUN_TIMEOUT: u64 = 600;
+// how long steam may take to start the game before `launch` stops waiting for it
+pub const LAUNCH_TIMEOUT: u64 = 120;
+// how many log entries `logs` shows
+pub const LOG_LINES: usize = 50;
+// how many plugins are downloaded at the same time
+pub const DOWNLOAD_JOBS: usize = 4;
 // googlebot user agent
 pub const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/111.0.0.0 Safari/537.36";
\ No newline at end of file
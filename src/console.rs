@@ -0,0 +1,157 @@
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use ansi_term::Color::{Red, Yellow};
+use ansi_term::{Colour, Style};
+use bepinex_installer::{InstallResult, Observer, Plugin, PluginStatus, Records, Step};
+use crate::progress::{MultiProgress, ProgressBar};
+use crate::tui::PluginBrowser;
+
+/// Shows installer events in the console and answers prompts from stdin.
+pub struct ConsoleObserver {
+    tui: bool,
+    // plugins named on the command line, they are installed without asking
+    names: Vec<String>,
+    progress: Mutex<Option<ProgressBar>>,
+    plugins: MultiProgress,
+    // line of every plugin in the multi progress, by plugin index
+    lines: Mutex<Vec<usize>>,
+}
+
+impl ConsoleObserver {
+    pub fn new(tui: bool) -> Self {
+        Self {
+            tui,
+            names: Vec::new(),
+            progress: Mutex::new(None),
+            plugins: MultiProgress::new(),
+            lines: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_names(mut self, names: &[String]) -> Self {
+        self.names = names.to_vec();
+        return self;
+    }
+
+    // ends the progress bar of the previous download before printing anything else
+    fn finish_progress(&self) {
+        if let Some(mut progress) = self.progress.lock().unwrap().take() {
+            progress.finish();
+        }
+    }
+
+    fn prompt(&self, plugins: &[Plugin]) -> Vec<usize> {
+        let mut selected = Vec::new();
+        for (index, plugin) in plugins.iter().enumerate() {
+            // prompt do you want to install plugin (Y/N)
+            println!("Do you want to install {}? [Y] Yes [N] No", plugin.name);
+            println!("{}{}", Style::new().bold().paint("Description: "), plugin.description);
+            if self.read_yes() {
+                selected.push(index);
+            }
+        }
+
+        return selected;
+    }
+
+    fn read_yes(&self) -> bool {
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input).is_err() {
+            return false;
+        }
+
+        return input.trim().to_lowercase() == "y";
+    }
+}
+
+impl Observer for ConsoleObserver {
+    fn step_started(&self, step: Step) {
+        self.finish_progress();
+
+        let gray = Colour::RGB(128, 128, 128);
+        match step {
+            Step::RemoveConflicts => println!("{}", gray.paint("Backing up and removing the other mod loader...")),
+            Step::InstallLoader => println!("{}", gray.paint("Installing the mod loader...")),
+            Step::FetchPlugins => println!("{}", gray.paint("Fetching plugins...")),
+            Step::SelectPlugins => {}
+            Step::InstallPlugins => println!("{}", gray.paint("Downloading plugins...")),
+            Step::FirstRun => println!("{}", gray.paint("Waiting for the mod loader to finish the first launch, this can take a few minutes...")),
+            // every download gets its own progress bar
+            Step::RepairDownload => {}
+            Step::RepairRestore => println!("{}", gray.paint("Restoring the files...")),
+            Step::WaitForExit => println!("{}", gray.paint("Waiting for the game to exit, everything is put back after that...")),
+        }
+    }
+
+    fn download_progress(&self, name: &str, downloaded: u64, total: Option<u64>) {
+        self.progress
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| ProgressBar::new(name))
+            .update(downloaded, total);
+    }
+
+    fn plugin_status(&self, index: usize, plugin: &Plugin, status: PluginStatus) {
+        let mut lines = self.lines.lock().unwrap();
+        match status {
+            PluginStatus::Queued => {
+                if lines.len() <= index {
+                    lines.resize(index + 1, 0);
+                }
+                lines[index] = self.plugins.add(&plugin.name, "Queued");
+            }
+            PluginStatus::Fetching => self.plugins.set_message(lines[index], "Fetching release..."),
+            PluginStatus::Downloading(downloaded, total) => self.plugins.update(lines[index], downloaded, total),
+            PluginStatus::Installed(version) => self.plugins.set_message(lines[index], &format!("Installed {}", version)),
+            PluginStatus::Failed(_) => self.plugins.set_message(lines[index], "Failed"),
+        }
+    }
+
+    fn warning(&self, message: &str) {
+        self.finish_progress();
+        println!("{}", Yellow.paint(message));
+    }
+
+    fn completed(&self, result: &InstallResult) {
+        self.finish_progress();
+
+        // reasons for every plugin that did not make it
+        let arrow = "\u{2514}\u{2500}\u{2500}\u{2500} ";
+        for (plugin, error) in &result.failed {
+            println!("{}", Red.paint(format!("Error downloading {}.\n{}Reason: {}", plugin.name, arrow, error)));
+            if let Some(suggestion) = error.suggestion() {
+                println!("{}", Colour::RGB(128, 128, 128).paint(format!("{}{}", arrow, suggestion)));
+            }
+        }
+    }
+
+    fn select_plugins(&self, plugins: &[Plugin], records: &Records) -> Vec<usize> {
+        if !self.names.is_empty() {
+            let mut selected = Vec::new();
+            for name in &self.names {
+                match plugins.iter().position(|x| x.name.eq_ignore_ascii_case(name)) {
+                    Some(index) => selected.push(index),
+                    None => self.warning(&format!("Plugin {} not found", name)),
+                }
+            }
+            return selected;
+        }
+
+        // the browser needs a real terminal, piped input falls back to the prompts
+        if self.tui && std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+            match PluginBrowser::new(plugins, records).run() {
+                Ok(Some(indices)) => return indices,
+                Ok(None) => return Vec::new(),
+                Err(err) => println!("{}", Yellow.paint(format!("Could not open the plugin browser: {}", err))),
+            }
+        }
+
+        return self.prompt(plugins);
+    }
+
+    fn confirm(&self, question: &str) -> bool {
+        self.finish_progress();
+        println!("{} [Y] Yes [N] No", question);
+        return self.read_yes();
+    }
+}
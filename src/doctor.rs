@@ -0,0 +1,293 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use zip::write::FileOptions;
+use crate::backup::Backup;
+use crate::config::{GAME_ID, VERSION};
+use crate::error::InstallerError;
+use crate::installer::{Installer, LoaderState, DOORSTOP_CONFIG};
+use crate::loader::LoaderKind;
+use crate::logs::Logs;
+use crate::records::Records;
+use crate::settings::Settings;
+use crate::steam::{Steam, SteamGame};
+
+// only the end of the log goes into the bundle, that is where the last launch is
+const LOG_LIMIT: usize = 512 * 1024;
+
+/// Health checks of the Steam, game and mod loader setup, and a zip of the results to send along with support requests.
+pub struct Doctor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+    // an earlier check failed or it does not apply to this system
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnosis {
+    pub installer_version: String,
+    pub os: String,
+    pub steam_path: Option<String>,
+    pub game: Option<SteamGame>,
+    pub checks: Vec<Check>,
+}
+
+impl Check {
+    fn new(name: &str, status: CheckStatus, detail: String) -> Self {
+        return Self {
+            name: name.to_string(),
+            status,
+            detail,
+            suggestion: None,
+        };
+    }
+
+    fn suggest(mut self, suggestion: &str) -> Self {
+        self.suggestion = Some(suggestion.to_string());
+        return self;
+    }
+}
+
+impl Diagnosis {
+    /// The checks that failed, warnings included.
+    pub fn problems(&self) -> usize {
+        return self.checks.iter().filter(|x| x.status == CheckStatus::Failed || x.status == CheckStatus::Warning).count();
+    }
+}
+
+impl Doctor {
+    pub fn diagnose() -> Diagnosis {
+        let mut checks = Vec::new();
+        let game_path = Settings::current().game_path.clone();
+
+        let steam_path = Steam::get_steam_path();
+        checks.push(match (&steam_path, &game_path) {
+            (Some(path), _) => Check::new("Steam", CheckStatus::Ok, format!("Found in {}", path)),
+            (None, Some(_)) => Check::new("Steam", CheckStatus::Warning, "Not found, the configured game path is used".to_string()),
+            (None, None) => Check::new("Steam", CheckStatus::Failed, "Not found".to_string())
+                .suggest("Install Steam and start it once, or set game_path in the settings"),
+        });
+
+        let game = Steam::find_game(GAME_ID);
+        checks.push(match &game {
+            Ok(game) => Check::new("Game", CheckStatus::Ok, format!("Found in {}", game.path)),
+            Err(err) => Check::new("Game", CheckStatus::Failed, err.to_string())
+                .suggest("Install Soulstone Survivors through Steam, or set game_path in the settings"),
+        });
+
+        let game = game.ok();
+        match &game {
+            Some(game) => Doctor::check_game(&mut checks, steam_path.as_deref(), game),
+            None => {
+                for name in ["Loader", "Other loaders", "Doorstop", "First launch", "Interop", "Plugin files", "Proton", "Log"] {
+                    checks.push(Check::new(name, CheckStatus::Skipped, "The game was not found".to_string()));
+                }
+            }
+        }
+
+        return Diagnosis {
+            installer_version: VERSION.to_string(),
+            os: std::env::consts::OS.to_string(),
+            steam_path,
+            game,
+            checks,
+        };
+    }
+
+    fn check_game(checks: &mut Vec<Check>, steam_path: Option<&str>, game: &SteamGame) {
+        let installer = Installer::new(&game.path);
+        let game_path = Path::new(&game.path);
+        let version = installer.loader_version().unwrap_or("unknown version".to_string());
+        let name = installer.loader().name();
+        let state = installer.loader_state();
+        checks.push(match state {
+            LoaderState::Enabled => Check::new("Loader", CheckStatus::Ok, format!("{} {} is installed", name, version)),
+            LoaderState::Disabled => Check::new("Loader", CheckStatus::Warning, format!("{} {} is disabled", name, version))
+                .suggest("Run `loader enable` to load plugins again"),
+            LoaderState::Missing => Check::new("Loader", CheckStatus::Failed, format!("{} is not installed", name))
+                .suggest("Run `install`"),
+        });
+
+        // two loaders starting with the game get in each other's way
+        let conflicts = installer.conflicts();
+        checks.push(match conflicts.is_empty() {
+            true => Check::new("Other loaders", CheckStatus::Ok, "None found".to_string()),
+            false => {
+                let found = conflicts.iter().map(|x| format!("{} ({})", x.path, x.owner())).collect::<Vec<_>>();
+                Check::new("Other loaders", CheckStatus::Failed, format!("Found {}", found.join(", "))).suggest("Run `conflicts remove`")
+            }
+        });
+        if state == LoaderState::Missing {
+            for check in ["Doorstop", "First launch", "Interop", "Plugin files", "Proton", "Log"] {
+                checks.push(Check::new(check, CheckStatus::Skipped, format!("{} is not installed", name)));
+            }
+            return;
+        }
+
+        // only BepInEx is started through doorstop
+        checks.push(match installer.loader().kind() {
+            LoaderKind::BepInEx => Doctor::check_doorstop(game_path, state),
+            _ => Check::new("Doorstop", CheckStatus::Skipped, format!("Not used by {}", name)),
+        });
+
+        checks.push(match installer.has_ran() {
+            true => Check::new("First launch", CheckStatus::Ok, format!("{} generated the interop assemblies", name)),
+            false => Check::new("First launch", CheckStatus::Warning, format!("The game was not started with {} yet", name))
+                .suggest("Run `first-run` or start the game once, plugins are loaded after that"),
+        });
+
+        let logs = Logs::new(&game.path);
+        checks.push(match logs.interop_outdated() {
+            false => Check::new("Interop", CheckStatus::Ok, "Up to date with the game".to_string()),
+            true => Check::new("Interop", CheckStatus::Warning, "The game was updated after the interop assemblies were generated".to_string())
+                .suggest(&format!("Delete {} and run `first-run`", installer.loader().interop_path())),
+        });
+
+        // every file we installed has to be where the record says
+        let records = Records::load(&game.path);
+        let mut broken = Vec::new();
+        for record in &records.plugins {
+            let missing = record.current_files().iter().filter(|x| !game_path.join(x).is_file()).count();
+            if missing > 0 {
+                broken.push(format!("{} ({} of {} files missing)", record.name, missing, record.files.len()));
+            }
+        }
+        checks.push(match broken.is_empty() {
+            true => Check::new("Plugin files", CheckStatus::Ok, format!("All files of {} plugins are present", records.plugins.len())),
+            false => Check::new("Plugin files", CheckStatus::Failed, broken.join(", "))
+                .suggest("Install the plugins again"),
+        });
+
+        checks.push(Doctor::check_proton(steam_path, installer.loader().proxy_dll()));
+
+        checks.push(match logs.read() {
+            Ok(entries) => {
+                let problems = logs.analyze(&entries);
+                match problems.is_empty() {
+                    true => Check::new("Log", CheckStatus::Ok, "No known problems".to_string()),
+                    false => Check::new("Log", CheckStatus::Warning, problems.iter().map(|x| x.message.clone()).collect::<Vec<_>>().join("; "))
+                        .suggest("Run `logs` for the details"),
+                }
+            }
+            Err(_) => Check::new("Log", CheckStatus::Skipped, "There is no log yet".to_string()),
+        });
+    }
+
+    fn check_doorstop(game_path: &Path, state: LoaderState) -> Check {
+        let config_path = game_path.join(DOORSTOP_CONFIG);
+        let config = match std::fs::read_to_string(&config_path) {
+            Ok(config) => config,
+            Err(_) => {
+                return Check::new("Doorstop", CheckStatus::Failed, format!("{} is missing", DOORSTOP_CONFIG))
+                    .suggest("Run `uninstall --loader` and `install`");
+            }
+        };
+
+        let enabled = Installer::doorstop_enabled(&config);
+        if state == LoaderState::Enabled && enabled == Some(false) {
+            return Check::new("Doorstop", CheckStatus::Failed, "enabled = false, BepInEx is not started".to_string())
+                .suggest("Run `loader enable`");
+        }
+
+        // the assembly doorstop starts, relative to the game folder
+        let target = config
+            .lines()
+            .filter_map(|x| x.split_once('='))
+            .find(|(key, _)| key.trim() == "target_assembly")
+            .map(|(_, value)| value.trim().replace('\\', "/"));
+        return match target {
+            Some(target) if game_path.join(&target).is_file() => Check::new("Doorstop", CheckStatus::Ok, format!("Starts {}", target)),
+            Some(target) => Check::new("Doorstop", CheckStatus::Failed, format!("{} does not exist", target))
+                .suggest("Run `uninstall --loader` and `install`"),
+            None => Check::new("Doorstop", CheckStatus::Failed, "No target_assembly set".to_string())
+                .suggest("Run `uninstall --loader` and `install`"),
+        };
+    }
+
+    // proton only loads the proxy dll of the loader when it is told to
+    #[cfg(target_os = "linux")]
+    fn check_proton(steam_path: Option<&str>, proxy_dll: &str) -> Check {
+        let options = steam_path.and_then(|x| Steam::launch_options(x, GAME_ID)).unwrap_or_default();
+        let dll = proxy_dll.trim_end_matches(".dll");
+        if options.contains(&format!("{}=n,b", dll)) || options.contains(&format!("{}=native,builtin", dll)) {
+            return Check::new("Proton", CheckStatus::Ok, format!("{} is overridden in the launch options", dll));
+        }
+
+        return Check::new("Proton", CheckStatus::Failed, format!("{} is not overridden in the launch options", dll))
+            .suggest(&format!("Set the launch options of the game in Steam to WINEDLLOVERRIDES=\"{}=n,b\" %command%", dll));
+    }
+
+    #[cfg(target_os = "windows")]
+    fn check_proton(_steam_path: Option<&str>, _proxy_dll: &str) -> Check {
+        return Check::new("Proton", CheckStatus::Skipped, "Not needed on Windows".to_string());
+    }
+
+    /// Writes the diagnosis, the installer records, the doorstop config, BepInEx.cfg and the end of the log
+    /// into a zip. The home folder, user name and GitHub token are replaced in everything.
+    pub fn write_bundle(diagnosis: &Diagnosis, folder: &Path) -> Result<PathBuf, InstallerError> {
+        std::fs::create_dir_all(folder).map_err(InstallerError::io(folder))?;
+        let bundle_path = folder.join(format!("bepinex-doctor-{}.zip", Backup::timestamp()));
+
+        let mut files = Vec::new();
+        let report = serde_json::to_string_pretty(diagnosis).map_err(std::io::Error::from).map_err(InstallerError::io(&bundle_path))?;
+        files.push(("doctor.json".to_string(), report));
+        if let Some(game) = &diagnosis.game {
+            let game_path = Path::new(&game.path);
+            for relative in ["BepInEx/installer.json", DOORSTOP_CONFIG, "BepInEx/config/BepInEx.cfg"] {
+                if let Ok(contents) = std::fs::read(game_path.join(relative)) {
+                    files.push((relative.to_string(), String::from_utf8_lossy(&contents).to_string()));
+                }
+            }
+            let log_path = LoaderKind::active().log_path();
+            if let Ok(contents) = std::fs::read(game_path.join(log_path)) {
+                let start = contents.len().saturating_sub(LOG_LIMIT);
+                files.push((log_path.to_string(), String::from_utf8_lossy(&contents[start..]).to_string()));
+            }
+        }
+
+        let file = std::fs::File::create(&bundle_path).map_err(InstallerError::io(&bundle_path))?;
+        let mut archive = zip::ZipWriter::new(file);
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, contents) in files {
+            archive.start_file(name, options).map_err(InstallerError::zip(&bundle_path))?;
+            archive.write_all(Doctor::sanitize(&contents).as_bytes()).map_err(InstallerError::io(&bundle_path))?;
+        }
+        archive.finish().map_err(InstallerError::zip(&bundle_path))?;
+
+        return Ok(bundle_path);
+    }
+
+    // paths give away the user name, tokens must never leave the machine
+    fn sanitize(text: &str) -> String {
+        let mut text = text.to_string();
+        if let Some(token) = &Settings::current().github_token {
+            text = text.replace(token, "********");
+        }
+        if let Some(home) = dirs::home_dir().map(|x| x.to_string_lossy().to_string()).filter(|x| x.len() > 1) {
+            // json and ini files escape or flip the separators
+            for variant in [home.clone(), home.replace('\\', "\\\\"), home.replace('\\', "/")] {
+                text = text.replace(&variant, "~");
+            }
+        }
+        let user = std::env::var("USERNAME").or_else(|_| std::env::var("USER")).unwrap_or_default();
+        if user.len() > 2 {
+            text = text.replace(&user, "<user>");
+        }
+
+        return text;
+    }
+}
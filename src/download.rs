@@ -0,0 +1,157 @@
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use reqwest::header::{ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use crate::error::InstallerError;
+use crate::hash::Hash;
+use crate::http::Http;
+
+// how often a download is resumed before giving up
+const MAX_ATTEMPTS: u32 = 3;
+
+pub struct Downloader;
+
+impl Downloader {
+    /// Streams `url` to `destination`, reporting `(downloaded, total)` bytes to `progress`.
+    /// The data is written to `<destination>.part` first. A leftover part file from an
+    /// interrupted download of the same url is resumed with a `Range` request instead of starting over,
+    /// `If-Range` makes the server send the whole file again when it changed in between.
+    pub fn download(url: &str, destination: &Path, size: Option<u64>, progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<u64, InstallerError> {
+        let part_path = Downloader::extension(destination, ".part");
+        let meta_path = Downloader::extension(destination, ".part.meta");
+
+        let mut attempt = 1;
+        loop {
+            // the folder might have been cleaned up since the last attempt
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent).map_err(InstallerError::io(parent))?;
+            }
+
+            match Downloader::download_part(url, &part_path, &meta_path, size, progress) {
+                Ok(downloaded) => {
+                    std::fs::rename(&part_path, destination).map_err(InstallerError::io(destination))?;
+                    let _ = std::fs::remove_file(&meta_path);
+                    return Ok(downloaded);
+                }
+                // connection errors are worth another try, bad responses are not
+                Err(InstallerError::NetworkError { .. }) | Err(InstallerError::IoError { .. }) if attempt < MAX_ATTEMPTS => {
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// The folder a download of `url` is kept in until it is unpacked, `<game>/temp/downloads/<name>-<url hash>`.
+    /// Every download gets its own, so parallel downloads never share files and an interrupted one is found again.
+    pub(crate) fn folder(game_path: &Path, name: &str, url: &str) -> PathBuf {
+        let name = name.chars().map(|x| if x.is_ascii_alphanumeric() { x } else { '-' }).collect::<String>();
+        return game_path.join("temp").join("downloads").join(format!("{}-{}", name, &Hash::text(url)[..16]));
+    }
+
+    // removes the download folders that are empty, only call it once no download runs anymore
+    pub(crate) fn clean_up(game_path: &Path) {
+        let downloads_path = game_path.join("temp").join("downloads");
+        if let Ok(entries) = std::fs::read_dir(&downloads_path) {
+            for entry in entries.flatten() {
+                let _ = std::fs::remove_dir(entry.path());
+            }
+        }
+        let _ = std::fs::remove_dir(&downloads_path);
+        let _ = std::fs::remove_dir(game_path.join("temp"));
+    }
+
+    fn download_part(url: &str, part_path: &Path, meta_path: &Path, size: Option<u64>, progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<u64, InstallerError> {
+        // a part file is only resumed when it belongs to this url and the server can tell whether it changed
+        let validator = match Downloader::read_meta(meta_path) {
+            Some((part_url, validator)) if part_url == url => validator,
+            _ => None,
+        };
+        if validator.is_none() && part_path.exists() {
+            std::fs::remove_file(part_path).map_err(InstallerError::io(part_path))?;
+        }
+        let offset = std::fs::metadata(part_path).map(|x| x.len()).unwrap_or(0);
+
+        // the part file is already complete
+        if offset > 0 && size == Some(offset) {
+            progress(offset, size);
+            return Ok(offset);
+        }
+
+        let mut request = Http::client()?.get(url);
+        if let (true, Some(validator)) = (offset > 0, &validator) {
+            request = request.header(RANGE, format!("bytes={}-", offset)).header(IF_RANGE, validator);
+        }
+        let mut response = request.send().map_err(InstallerError::network(url))?;
+
+        // the server might ignore the range and send the whole file again
+        let (mut downloaded, append) = match response.status() {
+            StatusCode::PARTIAL_CONTENT => (offset, true),
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                // the part file does not match the file on the server, start over
+                std::fs::remove_file(part_path).map_err(InstallerError::io(part_path))?;
+                let _ = std::fs::remove_file(meta_path);
+                return Downloader::download_part(url, part_path, meta_path, size, progress);
+            }
+            status if status.is_success() => (0, false),
+            _ => return Err(InstallerError::status(url, &response)),
+        };
+
+        // remember what the part file is a copy of, weak etags are not allowed in `If-Range`
+        let etag = response.headers().get(ETAG).and_then(|x| x.to_str().ok()).filter(|x| !x.starts_with("W/"));
+        let validator = etag.or(response.headers().get(LAST_MODIFIED).and_then(|x| x.to_str().ok()));
+        match validator {
+            Some(validator) => std::fs::write(meta_path, format!("{}\n{}\n", url, validator)).map_err(InstallerError::io(meta_path))?,
+            None => {
+                let _ = std::fs::remove_file(meta_path);
+            }
+        }
+        let total = size.or(response.content_length().map(|x| x + downloaded));
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(part_path)
+            .map_err(InstallerError::io(part_path))?;
+
+        let mut buffer = vec![0; 64 * 1024];
+        progress(downloaded, total);
+        loop {
+            let read = response.read(&mut buffer).map_err(InstallerError::io(part_path))?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read]).map_err(InstallerError::io(part_path))?;
+            downloaded += read as u64;
+            progress(downloaded, total);
+        }
+        file.flush().map_err(InstallerError::io(part_path))?;
+
+        if let Some(total) = total {
+            if downloaded < total {
+                let message = format!("Download ended after {} of {} bytes", downloaded, total);
+                return Err(InstallerError::io(part_path)(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, message)));
+            }
+        }
+
+        return Ok(downloaded);
+    }
+
+    // the url and the etag or last modified date of the file a part file belongs to
+    fn read_meta(meta_path: &Path) -> Option<(String, Option<String>)> {
+        let meta = std::fs::read_to_string(meta_path).ok()?;
+        let mut lines = meta.lines();
+        let url = lines.next()?.to_string();
+
+        return Some((url, lines.next().map(|x| x.to_string())));
+    }
+
+    fn extension(destination: &Path, extension: &str) -> PathBuf {
+        let mut path = destination.as_os_str().to_owned();
+        path.push(extension);
+        return PathBuf::from(path);
+    }
+}
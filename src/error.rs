@@ -0,0 +1,104 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Every way installing, removing or restoring can fail, with what it was working on.
+#[derive(Debug)]
+pub enum InstallerError {
+    NetworkError { url: String, source: reqwest::Error },
+    RateLimitError { url: String },
+    HttpError { url: String, status: u16 },
+    IoError { path: PathBuf, source: std::io::Error },
+    ZipError { path: PathBuf, source: zip::result::ZipError },
+    SerdeError { url: String, source: serde_json::Error },
+    SteamError(String),
+    ValidationError(String),
+    PluginError { plugin: String, source: Box<InstallerError> },
+}
+
+// implement display trait for custom error type
+impl std::fmt::Display for InstallerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            InstallerError::NetworkError { url, source } => write!(f, "Network Error: {} ({})", source, url),
+            InstallerError::RateLimitError { url } => write!(f, "Rate Limit Error: GitHub refused the request ({})", url),
+            InstallerError::HttpError { url, status } => write!(f, "HTTP Error: server responded with {} ({})", status, url),
+            InstallerError::IoError { path, source } => write!(f, "IO Error: {} ({})", source, path.display()),
+            InstallerError::ZipError { path, source } => write!(f, "Zip Error: {} ({})", source, path.display()),
+            InstallerError::SerdeError { url, source } => write!(f, "Serde Error: {} ({})", source, url),
+            InstallerError::SteamError(e) => write!(f, "Steam Error: {}", e),
+            InstallerError::ValidationError(e) => write!(f, "Validation Error: {}", e),
+            InstallerError::PluginError { plugin, source } => write!(f, "{}: {}", plugin, source),
+        }
+    }
+}
+
+impl std::error::Error for InstallerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InstallerError::NetworkError { source, .. } => Some(source),
+            InstallerError::IoError { source, .. } => Some(source),
+            InstallerError::ZipError { source, .. } => Some(source),
+            InstallerError::SerdeError { source, .. } => Some(source),
+            InstallerError::PluginError { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl InstallerError {
+    /// What the user can do about the error, if anything.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        return match self {
+            InstallerError::NetworkError { .. } => Some("Check your internet connection and try again."),
+            InstallerError::RateLimitError { .. } => Some("GitHub limits how often it can be asked, wait a few minutes and try again."),
+            InstallerError::HttpError { status: 404, .. } => Some("The file was removed from GitHub, try again later or tell the plugin author."),
+            InstallerError::HttpError { .. } => Some("The server is having trouble, try again later."),
+            InstallerError::IoError { source, .. } if source.kind() == ErrorKind::PermissionDenied => {
+                Some("Close the game and make sure the game folder is not read-only.")
+            }
+            InstallerError::IoError { source, .. } if source.kind() == ErrorKind::UnexpectedEof => {
+                Some("The download was cut off, run the installer again to resume it.")
+            }
+            InstallerError::IoError { .. } => Some("Make sure the game is closed and there is enough free disk space."),
+            InstallerError::ZipError { .. } => Some("The archive is damaged, delete the temp folder in the game folder and try again."),
+            InstallerError::SerdeError { .. } => Some("GitHub sent an unexpected response, try again later."),
+            InstallerError::SteamError(_) => Some("Make sure Steam and Soulstone Survivors are installed and Steam was started once."),
+            InstallerError::ValidationError(_) => None,
+            InstallerError::PluginError { source, .. } => source.suggestion(),
+        };
+    }
+
+    // map_err helpers that attach what was being worked on
+
+    pub(crate) fn io(path: impl AsRef<Path>) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        return move |source| InstallerError::IoError { path, source };
+    }
+
+    pub(crate) fn zip(path: impl AsRef<Path>) -> impl FnOnce(zip::result::ZipError) -> Self {
+        let path = path.as_ref().to_path_buf();
+        return move |source| InstallerError::ZipError { path, source };
+    }
+
+    pub(crate) fn network(url: &str) -> impl FnOnce(reqwest::Error) -> Self {
+        let url = url.to_string();
+        return move |source| InstallerError::NetworkError { url, source };
+    }
+
+    pub(crate) fn serde(url: &str) -> impl FnOnce(serde_json::Error) -> Self {
+        let url = url.to_string();
+        return move |source| InstallerError::SerdeError { url, source };
+    }
+
+    // the github api answers 403 or 429 once the rate limit is used up, other hosts mean something else by it
+    pub(crate) fn status(url: &str, response: &reqwest::blocking::Response) -> Self {
+        let status = response.status();
+        let limited = status == reqwest::StatusCode::FORBIDDEN || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+        let exhausted = response.headers().get("x-ratelimit-remaining").map(|x| x.as_bytes() == b"0").unwrap_or(false);
+        if limited && (url.starts_with("https://api.github.com/") || exhausted) {
+            return InstallerError::RateLimitError { url: url.to_string() };
+        }
+
+        return InstallerError::HttpError { url: url.to_string(), status: status.as_u16() };
+    }
+}
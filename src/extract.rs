@@ -0,0 +1,161 @@
+use std::io::{Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+// unix file type bits as stored in the external attributes of a zip entry
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+pub struct Extractor;
+
+pub struct RejectedEntry {
+    pub name: String,
+    pub reason: String,
+}
+
+pub struct ExtractResult {
+    pub extracted: Vec<PathBuf>,
+    pub rejected: Vec<RejectedEntry>,
+}
+
+impl Extractor {
+    /// Extracts every entry of `archive` below `root`.
+    /// Entries with absolute paths, `..` components or symlinks are skipped and
+    /// reported in `ExtractResult::rejected` instead of being written to disk.
+    pub fn extract<R: Read + Seek>(archive: &mut ZipArchive<R>, root: &Path) -> Result<ExtractResult, ZipError> {
+        let mut extracted = Vec::new();
+        let mut rejected = Vec::new();
+
+        std::fs::create_dir_all(root)?;
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let name = file.name().to_string();
+
+            // reject symlinks, they could point anywhere on the system
+            if let Some(mode) = file.unix_mode() {
+                if mode & S_IFMT == S_IFLNK {
+                    rejected.push(RejectedEntry { name, reason: "symlinks are not allowed".to_string() });
+                    continue;
+                }
+            }
+
+            // resolve the entry to a path inside root
+            let relative = match Extractor::sanitize(&name) {
+                Ok(relative) => relative,
+                Err(reason) => {
+                    rejected.push(RejectedEntry { name, reason });
+                    continue;
+                }
+            };
+            // empty paths are the root folder itself
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+            let out_path = root.join(&relative);
+
+            // never write through a symlink that already exists in the destination
+            if Extractor::has_symlink(root, &relative) {
+                rejected.push(RejectedEntry { name, reason: "destination contains a symlink".to_string() });
+                continue;
+            }
+
+            if file.is_dir() {
+                std::fs::create_dir_all(&out_path)?;
+                continue;
+            }
+
+            if let Some(parent) = out_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out_file = std::fs::File::create(&out_path)?;
+            std::io::copy(&mut file, &mut out_file)?;
+            extracted.push(out_path);
+        }
+
+        return Ok(ExtractResult {
+            extracted,
+            rejected,
+        });
+    }
+
+    // turns an entry name into a relative path, or returns the reason it was refused
+    fn sanitize(name: &str) -> Result<PathBuf, String> {
+        // archives built on windows may use backslashes as separators
+        let name = name.replace('\\', "/");
+
+        if name.contains('\0') {
+            return Err("path contains a null byte".to_string());
+        }
+        // drive letters like 'C:' are absolute on windows even without a leading slash
+        if name.starts_with('/') || name.chars().nth(1) == Some(':') {
+            return Err("absolute paths are not allowed".to_string());
+        }
+
+        let mut relative = PathBuf::new();
+        for component in Path::new(&name).components() {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => return Err("parent directory components are not allowed".to_string()),
+                Component::RootDir | Component::Prefix(_) => return Err("absolute paths are not allowed".to_string()),
+            }
+        }
+
+        return Ok(relative);
+    }
+
+    // checks if any existing part of root/relative is a symlink
+    fn has_symlink(root: &Path, relative: &Path) -> bool {
+        let mut path = root.to_path_buf();
+        for component in relative.components() {
+            path.push(component);
+            match std::fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => return true,
+                Ok(_) => continue,
+                // nothing exists from here on
+                Err(_) => return false,
+            }
+        }
+
+        return false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use super::*;
+
+    #[test]
+    fn rejects_paths_outside_the_root() {
+        assert_eq!(Extractor::sanitize("plugins/./Mod.dll").unwrap(), Path::new("plugins/Mod.dll"));
+        assert_eq!(Extractor::sanitize("plugins\\Mod.dll").unwrap(), Path::new("plugins/Mod.dll"));
+        for name in ["../Mod.dll", "plugins/../../Mod.dll", "..\\Mod.dll", "/etc/Mod.dll", "\\Mod.dll", "C:/Mod.dll", "C:\\Windows\\Mod.dll", "c:Mod.dll"] {
+            assert!(Extractor::sanitize(name).is_err(), "{} was accepted", name);
+        }
+    }
+
+    #[test]
+    fn extracts_only_safe_entries() {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for name in ["plugins/Mod.dll", "../Escaped.dll", "/Absolute.dll", "C:/Drive.dll"] {
+            writer.start_file(name, FileOptions::default()).unwrap();
+            writer.write_all(name.as_bytes()).unwrap();
+        }
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let parent = std::env::temp_dir().join(format!("bepinex_installer-extract-{}", std::process::id()));
+        let root = parent.join("root");
+        let _ = std::fs::remove_dir_all(&parent);
+        let result = Extractor::extract(&mut archive, &root).unwrap();
+
+        assert_eq!(result.extracted, vec![root.join("plugins/Mod.dll")]);
+        let rejected = result.rejected.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(rejected, vec!["../Escaped.dll", "/Absolute.dll", "C:/Drive.dll"]);
+        assert!(!parent.join("Escaped.dll").exists());
+        std::fs::remove_dir_all(&parent).unwrap();
+    }
+}
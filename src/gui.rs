@@ -0,0 +1,532 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use eframe::egui;
+use bepinex_installer::config::{FIRST_RUN_TIMEOUT, GAME_ID, TITLE, VERSION};
+use bepinex_installer::{GameUpdate, GameUpdates, Installer, InstallerError, LaunchMode, LoaderConflict, LoaderState, Observer, Plugin, PluginStatus, Plugins, Records, Settings, Steam, SteamGame};
+use crate::progress::format_bytes;
+
+/// Desktop front-end, only built with the `gui` feature.
+pub fn run() -> Result<(), eframe::Error> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([640.0, 560.0]),
+        ..Default::default()
+    };
+
+    return eframe::run_native(
+        &format!("{} - v{}", TITLE, VERSION),
+        options,
+        Box::new(|cc| Box::new(InstallerApp::new(&cc.egui_ctx))),
+    );
+}
+
+// everything the worker threads share with the window
+#[derive(Default)]
+struct State {
+    detecting: bool,
+    steam_path: Option<String>,
+    games: Vec<SteamGame>,
+    game: Option<SteamGame>,
+    // the mod loader plugins are installed for
+    loader_name: &'static str,
+    loader_installed: bool,
+    // false while the game starts unmodded
+    loader_enabled: bool,
+    // false until the first launch generated the interop assemblies
+    loader_ready: bool,
+    // the game build changed since the mods were set up
+    game_update: Option<GameUpdate>,
+    // files of other mod loaders, they would start together with ours
+    conflicts: Vec<LoaderConflict>,
+    plugins: Vec<Plugin>,
+    checked: Vec<bool>,
+    records: Records,
+    busy: bool,
+    loader_progress: Option<(u64, Option<u64>)>,
+    plugin_status: Vec<Option<PluginStatus>>,
+    // positions in `plugins` of the plugins being installed
+    installing: Vec<usize>,
+    messages: Vec<(bool, String)>,
+}
+
+struct InstallerApp {
+    state: Arc<Mutex<State>>,
+}
+
+// forwards installer events into the shared state and repaints the window
+struct GuiObserver {
+    state: Arc<Mutex<State>>,
+    ctx: egui::Context,
+}
+
+impl Observer for GuiObserver {
+    fn download_progress(&self, _name: &str, downloaded: u64, total: Option<u64>) {
+        self.state.lock().unwrap().loader_progress = Some((downloaded, total));
+        self.ctx.request_repaint();
+    }
+
+    fn plugin_status(&self, index: usize, _plugin: &Plugin, status: PluginStatus) {
+        let mut state = self.state.lock().unwrap();
+        let index = state.installing[index];
+        state.plugin_status[index] = Some(status);
+        self.ctx.request_repaint();
+    }
+
+    fn warning(&self, message: &str) {
+        self.state.lock().unwrap().messages.push((true, message.to_string()));
+        self.ctx.request_repaint();
+    }
+}
+
+impl InstallerApp {
+    fn new(ctx: &egui::Context) -> Self {
+        let app = Self {
+            state: Arc::new(Mutex::new(State::default())),
+        };
+        app.detect(ctx);
+        return app;
+    }
+
+    // look for steam, the game and the plugins without blocking the window
+    fn detect(&self, ctx: &egui::Context) {
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+        state.lock().unwrap().detecting = true;
+
+        std::thread::spawn(move || {
+            let steam_path = Steam::get_steam_path();
+            let games = steam_path.as_ref().and_then(|x| Steam::iterate_games(x)).unwrap_or_default();
+            let game = match &Settings::current().game_path {
+                Some(path) => Steam::game_at(path).ok(),
+                None => games.iter().find(|game| game.id == GAME_ID).cloned(),
+            };
+            {
+                let mut state = state.lock().unwrap();
+                let loader_state = game.as_ref().map(|x| Installer::new(&x.path).loader_state()).unwrap_or(LoaderState::Missing);
+                state.loader_name = game.as_ref().map(|x| Installer::new(&x.path).loader().name()).unwrap_or("BepInEx");
+                state.loader_installed = loader_state != LoaderState::Missing;
+                state.loader_enabled = loader_state == LoaderState::Enabled;
+                state.loader_ready = game.as_ref().map(|x| Installer::new(&x.path).has_ran()).unwrap_or(false);
+                state.game_update = game.as_ref().and_then(|x| GameUpdates::new(&x.path).check().ok().flatten());
+                state.conflicts = game.as_ref().map(|x| Installer::new(&x.path).conflicts()).unwrap_or_default();
+                state.records = game.as_ref().map(|x| Records::load(&x.path)).unwrap_or_default();
+                state.steam_path = steam_path;
+                state.games = games;
+                state.game = game;
+            }
+            ctx.request_repaint();
+
+            let plugins = Plugins::get_plugins();
+            let mut state = state.lock().unwrap();
+            match plugins {
+                Ok(plugins) => {
+                    let defaults = &Settings::current().default_plugins;
+                    state.checked = plugins.iter().map(|x| defaults.iter().any(|y| y.eq_ignore_ascii_case(&x.name))).collect();
+                    state.plugin_status = plugins.iter().map(|_| None).collect();
+                    state.plugins = plugins;
+                }
+                Err(err) => state.messages.push((true, format!("Could not load plugins: {}", describe(&err)))),
+            }
+            state.detecting = false;
+            ctx.request_repaint();
+        });
+    }
+
+    fn install(&self, ctx: &egui::Context) {
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+
+        let (game, install_loader, plugins) = {
+            let mut state = state.lock().unwrap();
+            let game = match state.game.clone() {
+                Some(game) => game,
+                None => return,
+            };
+            let indices = (0..state.plugins.len()).filter(|&x| state.checked[x]).collect::<Vec<_>>();
+            let plugins = indices.iter().map(|&x| state.plugins[x].clone()).collect::<Vec<_>>();
+            for &index in &indices {
+                state.plugin_status[index] = None;
+            }
+            state.installing = indices;
+            state.busy = true;
+            state.messages.clear();
+            (game, !state.loader_installed, plugins)
+        };
+
+        std::thread::spawn(move || {
+            let mut installer = Installer::new(&game.path);
+            installer.set_observer(Arc::new(GuiObserver {
+                state: state.clone(),
+                ctx: ctx.clone(),
+            }));
+
+            if install_loader {
+                let result = installer.install_loader();
+                let mut state = state.lock().unwrap();
+                match result {
+                    Ok(_) => {
+                        state.loader_installed = true;
+                        state.loader_enabled = true;
+                        let message = format!("{} installed!", state.loader_name);
+                        state.messages.push((false, message));
+                    }
+                    Err(err) => {
+                        // plugins are useless without the loader
+                        state.messages.push((true, describe(&err)));
+                        state.busy = false;
+                        ctx.request_repaint();
+                        return;
+                    }
+                }
+            }
+
+            if !plugins.is_empty() {
+                let result = installer.install_plugins(&plugins);
+                let mut state = state.lock().unwrap();
+                match result {
+                    Ok(results) => {
+                        for (plugin, result) in plugins.iter().zip(results) {
+                            match result {
+                                Ok(download) => state.messages.push((false, format!("{} {} installed!", plugin.name, download.version))),
+                                Err(err) => state.messages.push((true, describe(&err))),
+                            }
+                        }
+                    }
+                    Err(err) => state.messages.push((true, describe(&err))),
+                }
+                state.records = Records::load(&game.path);
+            }
+
+            // BepInEx.cfg values from the settings
+            let result = installer.apply_presets(&Settings::current().bepinex_cfg);
+            let mut state = state.lock().unwrap();
+            // the plugins are set up for this build of the game now
+            if GameUpdates::new(&game.path).acknowledge().is_ok() {
+                state.game_update = None;
+            }
+            match result {
+                Ok(presets) if presets.pending => {
+                    state.messages.push((true, "Start the game once and install again to apply the BepInEx.cfg settings".to_string()));
+                }
+                Ok(presets) if !presets.applied.is_empty() => {
+                    state.messages.push((false, format!("BepInEx.cfg updated: {}", presets.applied.join(", "))));
+                }
+                Ok(_) => {}
+                Err(err) => state.messages.push((true, describe(&err))),
+            }
+
+            state.busy = false;
+            ctx.request_repaint();
+        });
+    }
+
+    // waits for the game for minutes, keep it off the ui thread
+    fn first_run(&self, ctx: &egui::Context) {
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+        let game = {
+            let mut state = state.lock().unwrap();
+            let game = match state.game.clone() {
+                Some(game) => game,
+                None => return,
+            };
+            state.busy = true;
+            state.messages.clear();
+            game
+        };
+
+        std::thread::spawn(move || {
+            let installer = Installer::new(&game.path);
+            let result = installer.first_run(Duration::from_secs(FIRST_RUN_TIMEOUT));
+            let mut state = state.lock().unwrap();
+            match result {
+                Ok(_) => {
+                    state.loader_ready = true;
+                    let message = format!("{} finished its first launch!", state.loader_name);
+                    state.messages.push((false, message));
+                    if let Ok(presets) = installer.apply_presets(&Settings::current().bepinex_cfg) {
+                        if !presets.applied.is_empty() {
+                            state.messages.push((false, format!("BepInEx.cfg updated: {}", presets.applied.join(", "))));
+                        }
+                    }
+                }
+                Err(err) => state.messages.push((true, describe(&err))),
+            }
+            state.busy = false;
+            ctx.request_repaint();
+        });
+    }
+
+    // launches that change the loader wait for the game to exit, keep them off the ui thread
+    fn launch(&self, ctx: &egui::Context, mode: LaunchMode) {
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+        let game = {
+            let mut state = state.lock().unwrap();
+            let game = match state.game.clone() {
+                Some(game) => game,
+                None => return,
+            };
+            state.busy = true;
+            state.messages.clear();
+            game
+        };
+
+        std::thread::spawn(move || {
+            let installer = Installer::new(&game.path);
+            let result = installer.launch(&mode, false);
+            let mut state = state.lock().unwrap();
+            match result {
+                Ok(restore) if restore.is_empty() => state.messages.push((false, "Soulstone Survivors started!".to_string())),
+                Ok(_) => state.messages.push((false, "The game exited, the previous setup is back!".to_string())),
+                Err(err) => state.messages.push((true, describe(&err))),
+            }
+            state.busy = false;
+            ctx.request_repaint();
+        });
+    }
+
+    // the snapshot makes this slow for large setups
+    fn remove_conflicts(&self, ctx: &egui::Context) {
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+        let game = {
+            let mut state = state.lock().unwrap();
+            let game = match state.game.clone() {
+                Some(game) => game,
+                None => return,
+            };
+            state.busy = true;
+            state.messages.clear();
+            game
+        };
+
+        std::thread::spawn(move || {
+            let installer = Installer::new(&game.path);
+            let result = installer.remove_conflicts();
+            let mut state = state.lock().unwrap();
+            match result {
+                Ok(snapshot) => {
+                    state.conflicts = installer.conflicts();
+                    state.records = Records::load(&game.path);
+                    state.messages.push((false, format!("Removed, snapshot {} has a copy of everything!", snapshot.name)));
+                }
+                Err(err) => state.messages.push((true, describe(&err))),
+            }
+            state.busy = false;
+            ctx.request_repaint();
+        });
+    }
+
+    // the loader generates the interop assemblies again for the new build on the next launch
+    fn clear_interop(&self) {
+        let mut state = self.state.lock().unwrap();
+        let game = match &state.game {
+            Some(game) => game.clone(),
+            None => return,
+        };
+        let updates = GameUpdates::new(&game.path);
+        match updates.clear_interop().and_then(|_| updates.acknowledge()) {
+            Ok(_) => {
+                state.game_update = None;
+                state.loader_ready = false;
+                let message = format!("{} cleared, it is generated again on the next launch!", Installer::new(&game.path).loader().interop_path());
+                state.messages.push((false, message));
+            }
+            Err(err) => state.messages.push((true, describe(&err))),
+        }
+    }
+
+    // only renames two files, fast enough for the ui thread
+    fn toggle_loader(&self) {
+        let mut state = self.state.lock().unwrap();
+        let game = match &state.game {
+            Some(game) => game.clone(),
+            None => return,
+        };
+        let enabled = !state.loader_enabled;
+        match Installer::new(&game.path).set_loader_enabled(enabled) {
+            Ok(_) => state.loader_enabled = enabled,
+            Err(err) => state.messages.push((true, describe(&err))),
+        }
+    }
+}
+
+impl eframe::App for InstallerApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let mut install = false;
+        let mut toggle_loader = false;
+        let mut first_run = false;
+        let mut clear_interop = false;
+        let mut remove_conflicts = false;
+        let mut launch = None;
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let mut state = self.state.lock().unwrap();
+            let state = &mut *state;
+
+            ui.heading(TITLE);
+            ui.add_space(8.0);
+
+            // steam and game detection
+            match &state.steam_path {
+                Some(path) => ui.label(format!("Steam: {}", path)),
+                None if state.detecting => ui.label("Looking for Steam..."),
+                None => ui.colored_label(egui::Color32::RED, "Steam not found!"),
+            };
+            egui::CollapsingHeader::new(format!("{} games found", state.games.len())).show(ui, |ui| {
+                for game in &state.games {
+                    ui.label(format!("{} ({})", game.name, game.id));
+                }
+            });
+            match &state.game {
+                Some(game) => ui.label(format!("Soulstone Survivors: {}", game.path)),
+                None if state.detecting => ui.label("Looking for Soulstone Survivors..."),
+                None => ui.colored_label(egui::Color32::RED, "Soulstone Survivors not found!"),
+            };
+
+            // loader status, it can be turned off for unmodded play
+            if state.loader_installed {
+                ui.horizontal(|ui| {
+                    if state.loader_enabled {
+                        ui.colored_label(egui::Color32::GREEN, format!("{} is installed", state.loader_name));
+                    } else {
+                        ui.colored_label(egui::Color32::YELLOW, format!("{} is disabled, the game starts unmodded", state.loader_name));
+                    }
+                    let label = if state.loader_enabled { "Disable" } else { "Enable" };
+                    if ui.add_enabled(!state.busy, egui::Button::new(label)).clicked() {
+                        toggle_loader = true;
+                    }
+                });
+                // plugins do nothing before the first launch
+                if state.loader_enabled && !state.loader_ready {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::YELLOW, "Plugins are loaded after the game was started once");
+                        if ui.add_enabled(!state.busy, egui::Button::new("Start the game once")).clicked() {
+                            first_run = true;
+                        }
+                    });
+                }
+            } else if state.game.is_some() {
+                ui.colored_label(egui::Color32::YELLOW, format!("{} is not installed yet", state.loader_name));
+            }
+            if !state.conflicts.is_empty() {
+                let found = state.conflicts.iter().map(|x| format!("{} ({})", x.path, x.owner())).collect::<Vec<_>>();
+                ui.colored_label(egui::Color32::RED, format!("Another mod loader would start together with {}: {}", state.loader_name, found.join(", ")));
+                if ui.add_enabled(!state.busy, egui::Button::new("Back up and remove it")).clicked() {
+                    remove_conflicts = true;
+                }
+            }
+            if let Some(update) = &state.game_update {
+                ui.colored_label(egui::Color32::YELLOW, format!("The game was updated to build {}, these plugins might not work: {}", update.build, update.affected.join(", ")));
+                if update.interop && ui.add_enabled(!state.busy, egui::Button::new("Clear the interop assemblies")).clicked() {
+                    clear_interop = true;
+                }
+            }
+            if let (Some((downloaded, total)), true) = (state.loader_progress, state.busy) {
+                ui.add(progress_bar(downloaded, total));
+            }
+
+            ui.separator();
+            ui.strong("Plugins");
+            if state.detecting && state.plugins.is_empty() {
+                ui.spinner();
+            }
+            egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                for (index, plugin) in state.plugins.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(!state.busy, egui::Checkbox::new(&mut state.checked[index], &plugin.name));
+                        ui.label(format!("\u{2605} {}", plugin.stars));
+                        match state.records.find(&plugin.name) {
+                            Some(record) if record.disabled => {
+                                ui.colored_label(egui::Color32::GRAY, format!("disabled {}", record.version));
+                            }
+                            Some(record) => {
+                                ui.colored_label(egui::Color32::GREEN, format!("installed {}", record.version));
+                            }
+                            None => {}
+                        }
+                        match &state.plugin_status[index] {
+                            Some(PluginStatus::Queued) => {
+                                ui.label("Queued");
+                            }
+                            Some(PluginStatus::Fetching) => {
+                                ui.label("Fetching release...");
+                            }
+                            Some(PluginStatus::Downloading(downloaded, total)) => {
+                                ui.add(progress_bar(*downloaded, *total).desired_width(160.0));
+                            }
+                            Some(PluginStatus::Installed(version)) => {
+                                ui.colored_label(egui::Color32::GREEN, format!("Installed {}", version));
+                            }
+                            Some(PluginStatus::Failed(_)) => {
+                                ui.colored_label(egui::Color32::RED, "Failed");
+                            }
+                            None => {}
+                        }
+                    });
+                    ui.weak(&plugin.description);
+                }
+            });
+
+            ui.separator();
+            let can_install = state.game.is_some() && !state.busy && !state.detecting;
+            let label = if state.loader_installed { "Install selected plugins".to_string() } else { format!("Install {} and selected plugins", state.loader_name) };
+            ui.horizontal(|ui| {
+                if ui.add_enabled(can_install, egui::Button::new(label)).clicked() {
+                    install = true;
+                }
+                if ui.add_enabled(can_install && state.loader_installed, egui::Button::new("Play")).clicked() {
+                    launch = Some(LaunchMode::Modded);
+                }
+                if ui.add_enabled(can_install, egui::Button::new("Play without mods")).clicked() {
+                    launch = Some(LaunchMode::Vanilla);
+                }
+                if state.busy {
+                    ui.spinner();
+                }
+            });
+
+            for (error, message) in &state.messages {
+                if *error {
+                    ui.colored_label(egui::Color32::RED, message);
+                } else {
+                    ui.colored_label(egui::Color32::GREEN, message);
+                }
+            }
+        });
+
+        if install {
+            self.install(ctx);
+        }
+        if toggle_loader {
+            self.toggle_loader();
+        }
+        if first_run {
+            self.first_run(ctx);
+        }
+        if clear_interop {
+            self.clear_interop();
+        }
+        if remove_conflicts {
+            self.remove_conflicts(ctx);
+        }
+        if let Some(mode) = launch {
+            self.launch(ctx, mode);
+        }
+    }
+}
+
+// the error and what to do about it
+fn describe(error: &InstallerError) -> String {
+    return match error.suggestion() {
+        Some(suggestion) => format!("{}\n{}", error, suggestion),
+        None => format!("{}", error),
+    };
+}
+
+fn progress_bar(downloaded: u64, total: Option<u64>) -> egui::ProgressBar {
+    return match total {
+        Some(total) if total > 0 => egui::ProgressBar::new(downloaded as f32 / total as f32).show_percentage(),
+        _ => egui::ProgressBar::new(0.0).text(format_bytes(downloaded)),
+    };
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+use sha2::{Digest, Sha256};
+
+// sha256 of installed files, recorded at install time so `verify` can tell changed files apart
+pub(crate) struct Hash;
+
+impl Hash {
+    pub(crate) fn file(path: &Path) -> Result<String, std::io::Error> {
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+
+        return Ok(hasher.finalize().iter().map(|x| format!("{:02x}", x)).collect());
+    }
+
+    pub(crate) fn text(text: &str) -> String {
+        return Sha256::digest(text.as_bytes()).iter().map(|x| format!("{:02x}", x)).collect();
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use reqwest::blocking::Client;
+use reqwest::header::AUTHORIZATION;
+use crate::config::USER_AGENT;
+use crate::error::InstallerError;
+use crate::hash::Hash;
+use crate::settings::Settings;
+
+// every request goes through here so the proxy, token and cache settings apply everywhere
+pub(crate) struct Http;
+
+impl Http {
+    pub(crate) fn client() -> Result<Client, InstallerError> {
+        let settings = Settings::current();
+        let mut builder = Client::builder().user_agent(USER_AGENT);
+        if let Some(proxy) = &settings.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|x| InstallerError::ValidationError(format!("Invalid proxy {}: {}", proxy, x)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        return builder
+            .build()
+            .map_err(|x| InstallerError::ValidationError(format!("Could not set up the http client: {}", x)));
+    }
+
+    /// Gets a github api url, answers younger than the cache ttl are reused.
+    pub(crate) fn get_api(url: &str) -> Result<String, InstallerError> {
+        let settings = Settings::current();
+        let cache_path = Http::cache_path(url);
+        if let Some(cached) = cache_path.as_ref().and_then(|x| Http::read_cache(x, settings.cache_ttl)) {
+            return Ok(cached);
+        }
+
+        let mut request = Http::client()?.get(url);
+        if let Some(token) = &settings.github_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = request.send().map_err(InstallerError::network(url))?;
+
+        // a 403 means we are rate limited
+        if !response.status().is_success() {
+            return Err(InstallerError::status(url, &response));
+        }
+        let response = response.text().map_err(InstallerError::network(url))?;
+
+        // the cache is only a shortcut, failing to write it is fine
+        if let (Some(cache_path), true) = (cache_path, settings.cache_ttl > 0) {
+            if let Some(parent) = cache_path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            let _ = std::fs::write(cache_path, &response);
+        }
+
+        return Ok(response);
+    }
+
+    // DefaultHasher may change between Rust versions, sha256 keeps the cache valid across builds
+    fn cache_path(url: &str) -> Option<PathBuf> {
+        return dirs::cache_dir().map(|x| x.join("bepinex_installer").join(format!("{}.json", &Hash::text(url)[..16])));
+    }
+
+    fn read_cache(path: &PathBuf, ttl: u64) -> Option<String> {
+        let modified = std::fs::metadata(path).and_then(|x| x.modified()).ok()?;
+        let age = SystemTime::now().duration_since(modified).unwrap_or(Duration::MAX);
+        if age >= Duration::from_secs(ttl) {
+            return None;
+        }
+
+        return std::fs::read_to_string(path).ok();
+    }
+}
@@ -1,195 +1,1242 @@
-use std::path::Path;
-use ansi_term::Color::Red;
-use ansi_term::Style;
-use crate::config::{BEPINEX_URL, USER_AGENT};
-use crate::plugin::Plugin;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use serde::Serialize;
+use crate::backup::{Backup, Snapshot};
+use crate::cfg::CfgFile;
+use crate::config::{DOWNLOAD_JOBS, LAUNCH_TIMEOUT};
+use crate::download::Downloader;
+use crate::error::InstallerError;
+use crate::extract::RejectedEntry;
+use crate::hash::Hash;
+use crate::launcher::{LaunchMode, Launcher};
+use crate::loader::{LoaderKind, ModLoader};
+use crate::transaction::Transaction;
+use crate::updates::GameUpdates;
+use crate::plugin::{Plugin, PluginDownload};
 use crate::plugins::Plugins;
+use crate::observer::{Observer, PluginStatus, SilentObserver, Step};
+use crate::profiles::Profiles;
+use crate::records::{LaunchRestore, PluginRecord, Records};
+use crate::settings::Settings;
 
-pub struct Installer {
-    path: String,
+pub(crate) const DOORSTOP_CONFIG: &str = "doorstop_config.ini";
+// written by BepInEx on the first launch
+const BEPINEX_CFG: &str = "BepInEx/config/BepInEx.cfg";
+// proxy dlls and config of unity doorstop, loaders we do not know start through them too
+const DOORSTOP_FILES: [&str; 7] = [
+    "winhttp.dll",
+    "winhttp.dll.disabled",
+    "version.dll",
+    "version.dll.disabled",
+    "winmm.dll",
+    "doorstop_config.ini",
+    ".doorstop_version",
+];
+// holds the setup from before a profile launch when no profile was active
+const LAUNCH_PROFILE: &str = "before-launch";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoaderState {
+    Missing,
+    Enabled,
+    Disabled,
 }
 
-// custom error type
-#[derive(Debug)]
-pub enum InstallerError {
-    IoError(std::io::Error),
-    ZipError(zip::result::ZipError),
-    ReqwestError(reqwest::Error),
-    InstallError(String),
+pub struct Installer {
+    path: String,
+    jobs: usize,
+    observer: Arc<dyn Observer>,
+    loader: &'static dyn ModLoader,
 }
 
 pub struct InstallResult {
     pub plugins: Option<Vec<Plugin>>,
+    pub failed: Vec<(Plugin, InstallerError)>,
     pub installed_bepinex: Option<bool>,
+    pub presets: PresetResult,
 }
 
-// implement display trait for custom error type
-impl std::fmt::Display for InstallerError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            InstallerError::IoError(e) => write!(f, "IO Error: {}", e),
-            InstallerError::ZipError(e) => write!(f, "Zip Error: {}", e),
-            InstallerError::ReqwestError(e) => write!(f, "Reqwest Error: {}", e),
-            InstallerError::InstallError(e) => write!(f, "Install Error: {}", e),
-        }
+// the plugins of one install, split by whether they worked
+#[derive(Default)]
+struct Downloads {
+    installed: Vec<Plugin>,
+    failed: Vec<(Plugin, InstallerError)>,
+}
+
+// what happened to the configured BepInEx.cfg values, settings are named `[Section] Key`
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct PresetResult {
+    pub applied: Vec<String>,
+    // changed by the user, their value stays
+    pub kept: Vec<String>,
+    // BepInEx.cfg does not have them (yet)
+    pub missing: Vec<String>,
+    // BepInEx.cfg does not exist before the game was started once
+    pub pending: bool,
+}
+
+// a file or folder of another mod loader, both loaders would start with the game
+#[derive(Debug, Clone, Serialize)]
+pub struct LoaderConflict {
+    // relative to the game folder
+    pub path: String,
+    // `None` for doorstop files no known loader is there to claim
+    pub loader: Option<LoaderKind>,
+}
+
+impl LoaderConflict {
+    pub fn owner(&self) -> &'static str {
+        return match self.loader {
+            Some(kind) => kind.loader().name(),
+            None => "an unknown doorstop loader",
+        };
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileProblem {
+    Missing,
+    // the contents differ from what was installed
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileIssue {
+    // where the file is right now, relative to the game folder
+    pub path: String,
+    // the name of the loader or of the plugin
+    pub owner: String,
+    pub problem: FileProblem,
+}
+
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct VerifyResult {
+    pub checked: usize,
+    pub issues: Vec<FileIssue>,
+    // installed before hashes were recorded, their files were only checked for presence
+    pub unverified: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct RepairResult {
+    pub restored: Vec<FileIssue>,
+    // owners that could not be downloaded again, with the reason
+    pub failed: BTreeMap<String, String>,
+}
+
 impl Installer {
     pub fn new(path: &str) -> Self {
         Self {
             path: path.to_string(),
+            jobs: DOWNLOAD_JOBS,
+            observer: Arc::new(SilentObserver),
+            loader: LoaderKind::active(),
         }
     }
 
+    /// The mod loader this installer sets up, see `LoaderKind::active`.
+    pub fn loader(&self) -> &'static dyn ModLoader {
+        return self.loader;
+    }
+
+    // receives progress, warnings and prompts, nothing is shown without one
+    pub fn set_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observer = observer;
+    }
+
+    // how many plugins are downloaded at the same time
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = jobs;
+    }
+
     pub fn install(&self) -> Result<InstallResult, InstallerError> {
         let mut installed_bepinex: Option<bool> = None;
 
         // check if installed
         if !self.is_installed() {
             // download bepinex
-            self.setup()?;
+            self.observer.step_started(Step::InstallLoader);
+            self.install_loader()?;
             installed_bepinex = Some(true);
+        } else {
+            // the folders alone say nothing about deleted or quarantined files
+            let mut verified = VerifyResult::default();
+            self.verify_loader(&Records::load(&self.path), &mut verified, false);
+            if !verified.issues.is_empty() {
+                let files = verified.issues.iter().map(|x| x.path.clone()).collect::<Vec<_>>();
+                self.observer.warning(&format!("{} is missing {}, run `repair` to restore it", self.loader.name(), files.join(", ")));
+            }
         }
 
-
         // download plugins
-        let plugins = self.download_plugins()?;
+        let downloads = self.download_plugins()?;
+
+        // the plugins are set up for this build of the game now
+        if let Err(err) = GameUpdates::new(&self.path).acknowledge() {
+            self.observer.warning(&format!("Could not record the game build: {}", err));
+        }
+
+        // a broken BepInEx.cfg should not fail the whole install
+        let presets = match self.apply_presets(&Settings::current().bepinex_cfg) {
+            Ok(presets) => presets,
+            Err(err) => {
+                self.observer.warning(&format!("Could not apply the BepInEx.cfg settings: {}", err));
+                PresetResult::default()
+            }
+        };
 
-        return Ok(InstallResult {
-            plugins: Some(plugins),
+        let result = InstallResult {
+            plugins: Some(downloads.installed),
+            failed: downloads.failed,
             installed_bepinex,
+            presets,
+        };
+        self.observer.completed(&result);
+
+        return Ok(result);
+    }
+
+    fn download_plugins(&self) -> Result<Downloads, InstallerError> {
+        self.observer.step_started(Step::FetchPlugins);
+        let plugins = Plugins::get_plugins()?;
+
+        // pick every plugin first so the downloads can run without prompts in between
+        self.observer.step_started(Step::SelectPlugins);
+        let records = Records::load(&self.path);
+        let indices = self.observer.select_plugins(&plugins, &records);
+        let selected = plugins
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| indices.contains(index))
+            .map(|(_, plugin)| plugin)
+            .collect::<Vec<_>>();
+        if selected.is_empty() {
+            return Ok(Downloads::default());
+        }
+
+        self.observer.step_started(Step::InstallPlugins);
+        let results = self.install_plugins(&selected)?;
+
+        // split what worked from what did not
+        let mut downloads = Downloads::default();
+        for (plugin, result) in selected.into_iter().zip(results) {
+            match result {
+                Ok(download) => {
+                    for entry in download.rejected {
+                        self.observer.warning(&format!("Skipped {} in {}: {}", entry.name, plugin.name, entry.reason));
+                    }
+                    downloads.installed.push(plugin);
+                }
+                Err(error) => downloads.failed.push((plugin, error)),
+            }
+        }
+
+        return Ok(downloads);
+    }
+
+    /// Downloads `plugins` in parallel and records the installed files.
+    /// Progress is reported to the observer with the index of the plugin it is about.
+    pub fn install_plugins(&self, plugins: &[Plugin]) -> Result<Vec<Result<PluginDownload, InstallerError>>, InstallerError> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new((0..plugins.len()).map(|_| None).collect::<Vec<_>>());
+        let status = |index: usize, status: PluginStatus| self.observer.plugin_status(index, &plugins[index], status);
+        for index in 0..plugins.len() {
+            status(index, PluginStatus::Queued);
+        }
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs.clamp(1, plugins.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= plugins.len() {
+                        break;
+                    }
+
+                    status(index, PluginStatus::Fetching);
+                    let result = plugins[index].download(&self.path, &mut |downloaded, total| status(index, PluginStatus::Downloading(downloaded, total)));
+                    match &result {
+                        Ok(download) => status(index, PluginStatus::Installed(download.version.clone())),
+                        Err(err) => status(index, PluginStatus::Failed(format!("{}", err))),
+                    }
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
         });
+        Downloader::clean_up(Path::new(&self.path));
+
+        // remember what went where so plugins can be found again later
+        let mut records = Records::load(&self.path);
+        let results = results
+            .into_inner()
+            .unwrap_or_else(|x| x.into_inner())
+            .into_iter()
+            .map(|x| x.unwrap_or(Err(InstallerError::ValidationError("Download did not run".to_string()))))
+            .collect::<Vec<_>>();
+        for (plugin, result) in plugins.iter().zip(&results) {
+            if let Ok(download) = result {
+                // a reinstalled plugin is enabled again, its disabled copy is stale
+                if let Some(previous) = records.find(&plugin.name).filter(|x| x.disabled) {
+                    for file in previous.current_files() {
+                        let _ = std::fs::remove_file(Path::new(&self.path).join(file));
+                    }
+                }
+                records.insert(PluginRecord {
+                    name: plugin.name.clone(),
+                    version: download.version.clone(),
+                    repo: plugin.repo.clone(),
+                    files: download.files.iter().map(|x| Records::relative_path(&self.path, x)).collect(),
+                    disabled: false,
+                    // without hashes the plugin is only checked for missing files
+                    hashes: Installer::hash_files(&self.path, &download.files).unwrap_or_default(),
+                });
+            }
+        }
+        records.save(&self.path)?;
+
+        return Ok(results);
     }
 
-    fn download_plugins(&self) -> Result<Vec<Plugin>, InstallerError> {
-        let plugins = Plugins::get_plugins().map_err(InstallerError::ReqwestError)?;
-        let mut installed_plugins = Vec::new();
-
-        for plugin in plugins {
-            // prompt do you want to install plugin (Y/N)
-            let name = plugin.name.clone();
-            let description = plugin.description.clone();
-            println!("Do you want to install {}? [Y] Yes [N] No", name);
-            println!("{}{}", Style::new().bold().paint("Description: "), description);
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).unwrap();
-            let input = input.trim().to_lowercase();
-            if input != "y" {
+    /// Downloads and installs the mod loader, the download is reported to the observer.
+    pub fn install_loader(&self) -> Result<(), InstallerError> {
+        self.resolve_conflicts()?;
+
+        // stage everything in the temp folder first, the game folder is only touched on commit
+        let game_path = Path::new(&self.path);
+        let temp_path = game_path.join("temp");
+        let transaction = Transaction::new(game_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+        for entry in self.download_loader(&transaction.staging_path())? {
+            self.observer.warning(&format!("Skipped {}: {}", entry.name, entry.reason));
+        }
+
+        // move the staged files into the game folder, replaced files are restored if this fails
+        let files = transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        let mut records = Records::load(&self.path);
+        records.loader = Some(self.loader.version().to_string());
+        records.loader_files = Installer::hash_files(&self.path, &files).unwrap_or_default();
+        records.save(&self.path)?;
+
+        // not every loader zip brings the plugins folder along
+        let plugins_path = game_path.join(self.loader.plugins_path());
+        std::fs::create_dir_all(&plugins_path).map_err(InstallerError::io(&plugins_path))?;
+
+        return Ok(());
+    }
+
+    // another loader in the game folder is only removed when the observer agrees, the install stops otherwise
+    fn resolve_conflicts(&self) -> Result<(), InstallerError> {
+        let conflicts = self.conflicts();
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        let mut names = conflicts.iter().map(|x| x.owner()).collect::<Vec<_>>();
+        names.dedup();
+        let names = names.join(" and ");
+        for conflict in &conflicts {
+            self.observer.warning(&format!("{} belongs to {}", conflict.path, conflict.owner()));
+        }
+        let question = format!("Found files of {}, they would start together with {}. Back them up and remove them?", names, self.loader.name());
+        if !self.observer.confirm(&question) {
+            return Err(InstallerError::ValidationError(format!(
+                "Found files of {}, run `conflicts remove --mod-loader {}` to back them up and remove them before installing {}",
+                names,
+                self.loader.name().to_lowercase(),
+                self.loader.name()
+            )));
+        }
+
+        self.observer.step_started(Step::RemoveConflicts);
+        let snapshot = self.remove_conflicts()?;
+        self.observer.warning(&format!("Removed the files of {}, snapshot {} has a copy", names, snapshot.name));
+
+        return Ok(());
+    }
+
+    /// Files and folders of the other mod loaders in the game folder, by the loader they belong to.
+    /// Doorstop files without the folder of a known loader next to them belong to a loader we do not know.
+    pub fn conflicts(&self) -> Vec<LoaderConflict> {
+        let game_path = Path::new(&self.path);
+        let mut conflicts = Vec::new();
+        let mut claimed = Vec::new();
+        for kind in LoaderKind::ALL {
+            let loader = kind.loader();
+            if !game_path.join(loader.signature()).exists() {
                 continue;
             }
+            claimed.extend(loader.markers().iter().copied());
+            if kind == self.loader.kind() {
+                continue;
+            }
+            for path in std::iter::once(loader.signature()).chain(loader.markers().iter().copied()) {
+                if game_path.join(path).exists() {
+                    conflicts.push(LoaderConflict {
+                        path: path.to_string(),
+                        loader: Some(kind),
+                    });
+                }
+            }
+        }
+        for file in DOORSTOP_FILES {
+            if game_path.join(file).exists() && !claimed.contains(&file) {
+                conflicts.push(LoaderConflict {
+                    path: file.to_string(),
+                    loader: None,
+                });
+            }
+        }
+
+        return conflicts;
+    }
+
+    /// Snapshots the mod setup and removes every other mod loader with its plugins, returns the snapshot.
+    pub fn remove_conflicts(&self) -> Result<Snapshot, InstallerError> {
+        let game_path = Path::new(&self.path);
+        let had_records = Records::records_path(&self.path).exists();
+        let mut records = Records::load(&self.path);
+
+        // everything that gets removed goes into the snapshot, nothing is removed when a file is missing from it
+        let conflicts = self.conflicts();
+        let mut paths = Vec::new();
+        for conflict in &conflicts {
+            match conflict.loader {
+                Some(kind) => paths.extend(kind.loader().paths().iter().copied()),
+                None => paths.push(conflict.path.as_str()),
+            }
+        }
+        let snapshot = Backup::new(&self.path).create_with(&paths)?;
+        let entries = Backup::entries(&snapshot)?;
+        let mut files = Vec::new();
+        for path in &paths {
+            Transaction::collect_files(game_path, Path::new(path), &mut files).map_err(InstallerError::io(game_path.join(path)))?;
+        }
+        if let Some(missing) = files.iter().find(|x| !entries.contains(&Backup::entry_name(x))) {
+            return Err(InstallerError::ValidationError(format!(
+                "Snapshot {} does not contain {}, nothing was removed",
+                snapshot.name,
+                missing.display()
+            )));
+        }
+
+        let mut removed = Vec::new();
+        for kind in conflicts.iter().filter_map(|x| x.loader) {
+            if !removed.contains(&kind) {
+                kind.loader().uninstall(game_path)?;
+                removed.push(kind);
+            }
+        }
+        // of a loader we do not know only the doorstop files are known
+        let mut transaction = Transaction::new(game_path, &game_path.join("temp")).map_err(InstallerError::io(game_path.join("temp")))?;
+        for conflict in conflicts.iter().filter(|x| x.loader.is_none()) {
+            transaction.remove(Path::new(&conflict.path));
+        }
+        transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        // the records can be in a removed folder, keep those of the plugins that are still there
+        if had_records {
+            let kind = self.loader.kind();
+            records.plugins.retain(|x| x.files.iter().all(|x| LoaderKind::plugin_file(x).map(|(loader, _)| loader.kind()) == Some(kind)));
+            if !self.is_installed() {
+                records.loader = None;
+                records.loader_files.clear();
+            }
+            records.save(&self.path)?;
+        }
+
+        return Ok(snapshot);
+    }
+
+    // downloads the loader and extracts it into `target`, laid out like the game folder
+    fn download_loader(&self, target: &Path) -> Result<Vec<RejectedEntry>, InstallerError> {
+        let mut progress = |downloaded, total| self.observer.download_progress(self.loader.name(), downloaded, total);
+        let result = self.loader.download(Path::new(&self.path), target, &mut progress);
+        Downloader::clean_up(Path::new(&self.path));
+
+        return result;
+    }
+
+    // sha256 of `files`, by their path relative to `root`
+    fn hash_files(root: &str, files: &[PathBuf]) -> Result<BTreeMap<String, String>, InstallerError> {
+        let mut hashes = BTreeMap::new();
+        for file in files {
+            hashes.insert(Records::relative_path(root, file), Hash::file(file).map_err(InstallerError::io(file))?);
+        }
+
+        return Ok(hashes);
+    }
+
+    /// Checks every file of the loader and the installed plugins against the hashes recorded when they were installed.
+    pub fn verify(&self) -> Result<VerifyResult, InstallerError> {
+        let records = Records::load(&self.path);
+        if !self.is_installed() && records.loader_files.is_empty() {
+            return Err(InstallerError::ValidationError(format!("{} is not installed", self.loader.name())));
+        }
 
-            // proceed to download plugin
-            println!("Downloading {}...", name);
-            let result = plugin.download(self.path.as_str());
-            if result.is_err() {
-                // get error
-                let error = result.err().unwrap();
-                let arrow = "\u{2514}\u{2500}\u{2500}\u{2500} ";
-                println!("{}", Red.paint(format!("Error downloading {}.\n{}Reason: {}", name, arrow, error)));
+        let mut result = VerifyResult::default();
+        self.verify_loader(&records, &mut result, true);
+        let game_path = Path::new(&self.path);
+        for record in &records.plugins {
+            if record.hashes.is_empty() {
+                result.unverified.push(record.name.clone());
+            }
+            for (file, current) in record.files.iter().zip(record.current_files()) {
+                let problem = Installer::check_file(&game_path.join(&current), record.hashes.get(file), true);
+                result.checked += 1;
+                if let Some(problem) = problem {
+                    result.issues.push(FileIssue {
+                        path: current,
+                        owner: record.name.clone(),
+                        problem,
+                    });
+                }
+            }
+        }
+
+        return Ok(result);
+    }
+
+    // without `hash` only missing files are found, hashing the whole loader takes a moment
+    fn verify_loader(&self, records: &Records, result: &mut VerifyResult, hash: bool) {
+        let game_path = Path::new(&self.path);
+        let mut files = records.loader_files.iter().map(|(x, hash)| (x.clone(), Some(hash))).collect::<Vec<_>>();
+        if files.is_empty() {
+            // installed by hand or before hashes were recorded, the files the loader needs to start are known at least
+            result.unverified.push(self.loader.name().to_string());
+            files = self.loader.essential_files().iter().map(|x| (x.to_string(), None)).collect();
+        }
+
+        for (file, expected) in files {
+            let current = self.loader_location(&file);
+            let expected = if Installer::user_editable(&file) { None } else { expected };
+            result.checked += 1;
+            if let Some(problem) = Installer::check_file(&game_path.join(&current), expected, hash) {
+                result.issues.push(FileIssue {
+                    path: current,
+                    owner: self.loader.name().to_string(),
+                    problem,
+                });
+            }
+        }
+    }
+
+    fn check_file(path: &Path, expected: Option<&String>, hash: bool) -> Option<FileProblem> {
+        if !path.is_file() {
+            return Some(FileProblem::Missing);
+        }
+        // a file that cannot be read counts as changed, antivirus software locks what it quarantines
+        return match expected {
+            Some(expected) if hash && Hash::file(path).ok().as_ref() != Some(expected) => Some(FileProblem::Changed),
+            _ => None,
+        };
+    }
+
+    // the proxy dll is renamed while the loader is disabled
+    fn loader_location(&self, file: &str) -> String {
+        let game_path = Path::new(&self.path);
+        let proxy_dll = self.loader.proxy_dll();
+        let disabled = format!("{}.disabled", proxy_dll);
+        if file == proxy_dll && !game_path.join(proxy_dll).exists() && game_path.join(&disabled).exists() {
+            return disabled;
+        }
+
+        return file.to_string();
+    }
+
+    // doorstop_config.ini is rewritten by `loader enable` and configs are meant to be edited, only missing ones count
+    fn user_editable(file: &str) -> bool {
+        return file == DOORSTOP_CONFIG || file.to_lowercase().starts_with("bepinex/config/");
+    }
+
+    /// Downloads the loader and every plugin with missing or changed files again and puts back only those files.
+    /// Plugins are restored at the version that is installed, files without recorded hashes are compared
+    /// against the download and get their hashes recorded.
+    pub fn repair(&self) -> Result<RepairResult, InstallerError> {
+        let verified = self.verify()?;
+        let mut records = Records::load(&self.path);
+        let owners = verified.issues.iter().map(|x| x.owner.clone()).chain(verified.unverified).collect::<Vec<_>>();
+
+        let game_path = Path::new(&self.path);
+        let temp_path = game_path.join("temp");
+        let scratch_path = temp_path.join(format!("repair-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&scratch_path);
+        let mut transaction = Transaction::new(game_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+        let mut result = RepairResult::default();
+        let mut loader_files = None;
+        let mut plugin_hashes = Vec::new();
+
+        if owners.iter().any(|x| x == self.loader.name()) {
+            self.observer.step_started(Step::RepairDownload);
+            match self.stage_loader_repair(&mut transaction, &records, &scratch_path.join("loader"), &mut result.restored) {
+                Ok(hashes) => loader_files = Some(hashes),
+                Err(err) => {
+                    result.failed.insert(self.loader.name().to_string(), err.to_string());
+                }
+            }
+        }
+        for record in &records.plugins {
+            if !owners.contains(&record.name) {
                 continue;
             }
-            println!("{} downloaded successfully!", name);
-            installed_plugins.push(plugin);
+            self.observer.step_started(Step::RepairDownload);
+            let target = scratch_path.join("plugins").join(plugin_hashes.len().to_string());
+            match self.stage_plugin_repair(&mut transaction, record, &target, &mut result.restored) {
+                Ok(hashes) => plugin_hashes.push((record.name.clone(), hashes)),
+                Err(err) => {
+                    result.failed.insert(record.name.clone(), err.to_string());
+                }
+            }
         }
 
-        return Ok(installed_plugins);
+        if !owners.is_empty() {
+            self.observer.step_started(Step::RepairRestore);
+        }
+        let committed = transaction.commit().map_err(InstallerError::io(game_path));
+        let _ = std::fs::remove_dir_all(&scratch_path);
+        committed?;
+
+        // the restored files are what was installed, remember them for the next verify
+        if let Some(hashes) = loader_files {
+            records.loader_files = hashes;
+        }
+        for (name, hashes) in plugin_hashes {
+            if let Some(record) = records.plugins.iter_mut().find(|x| x.name == name) {
+                record.hashes = hashes;
+            }
+        }
+        records.save(&self.path)?;
+
+        return Ok(result);
     }
 
-    fn setup(&self) -> Result<(), InstallerError> {
-        // create temp folder
-        let temp_path = Path::new(&self.path).join("temp");
-        if !temp_path.exists() {
-            std::fs::create_dir(&temp_path).map_err(InstallerError::IoError)?;
+    // stages the loader files that differ from a fresh download, returns the hashes of the download
+    fn stage_loader_repair(&self, transaction: &mut Transaction, records: &Records, target: &Path, restored: &mut Vec<FileIssue>) -> Result<BTreeMap<String, String>, InstallerError> {
+        // a different version would leave a mix of both behind
+        if records.loader.as_deref() != Some(self.loader.version()) {
+            return Err(InstallerError::ValidationError(format!(
+                "{} {} was not installed by this installer, run `uninstall --loader` and `install` instead",
+                self.loader.name(),
+                records.loader.as_deref().unwrap_or("of an unknown version")
+            )));
         }
 
-        // download bepinex
-        let bepinex_zip_path = temp_path.join("bepinex.zip");
-        let bepinex_zip = reqwest::blocking::Client::new()
-            .get(BEPINEX_URL)
-            .header("User-Agent", USER_AGENT)
-            .send().map_err(InstallerError::ReqwestError)?;
+        self.download_loader(target)?;
+        let mut files = Vec::new();
+        Transaction::collect_files(target, Path::new(""), &mut files).map_err(InstallerError::io(target))?;
+        let files = files.into_iter().map(|x| target.join(x)).collect::<Vec<_>>();
+        let hashes = Installer::hash_files(&target.to_string_lossy(), &files)?;
+        for (file, hash) in &hashes {
+            let current = self.loader_location(file);
+            let expected = if Installer::user_editable(file) { None } else { Some(hash) };
+            self.stage_restore(transaction, &target.join(file), &current, expected, self.loader.name(), restored)?;
+        }
+
+        return Ok(hashes);
+    }
+
+    // stages the plugin files that differ from a fresh download of the installed version
+    fn stage_plugin_repair(&self, transaction: &mut Transaction, record: &PluginRecord, target: &Path, restored: &mut Vec<FileIssue>) -> Result<BTreeMap<String, String>, InstallerError> {
+        let mut progress = |downloaded, total| self.observer.download_progress(&record.name, downloaded, total);
+        Plugin::fetch_version(&record.repo, &record.version, target, &mut progress)?;
+
+        let mut hashes = BTreeMap::new();
+        for (file, current) in record.files.iter().zip(record.current_files()) {
+            // release zips are laid out like the plugins folder
+            let source = match LoaderKind::plugin_file(file) {
+                Some((_, rest)) => target.join(rest),
+                None => continue,
+            };
+            if !source.is_file() {
+                return Err(InstallerError::ValidationError(format!("{} {} no longer contains {}", record.name, record.version, file)));
+            }
+            let hash = Hash::file(&source).map_err(InstallerError::io(&source))?;
+            self.stage_restore(transaction, &source, &current, Some(&hash), &record.name, restored)?;
+            hashes.insert(file.clone(), hash);
+        }
 
-        let bepinex_zip = bepinex_zip.bytes().map_err(InstallerError::ReqwestError)?;
-        std::fs::write(&bepinex_zip_path, &bepinex_zip).map_err(InstallerError::IoError)?;
+        return Ok(hashes);
+    }
 
-        // extract bepinex
-        let bepinex_zip = std::fs::File::open(&bepinex_zip_path).map_err(InstallerError::IoError)?;
-        let mut bepinex_zip = zip::ZipArchive::new(bepinex_zip).map_err(InstallerError::ZipError)?;
-        bepinex_zip.extract(&temp_path).map_err(InstallerError::ZipError)?;
+    fn stage_restore(&self, transaction: &mut Transaction, source: &Path, current: &str, expected: Option<&String>, owner: &str, restored: &mut Vec<FileIssue>) -> Result<(), InstallerError> {
+        let problem = match Installer::check_file(&Path::new(&self.path).join(current), expected, true) {
+            Some(problem) => problem,
+            None => return Ok(()),
+        };
+        transaction.stage_file(source, Path::new(current)).map_err(InstallerError::io(source))?;
+        restored.push(FileIssue {
+            path: current.to_string(),
+            owner: owner.to_string(),
+            problem,
+        });
 
+        return Ok(());
+    }
 
-        // wait for extraction to finish
-        std::thread::sleep(std::time::Duration::from_secs(1));
+    /// Writes the given BepInEx.cfg values, by section and key. A value is only written while the setting
+    /// still has its default or the value we wrote last time, so changes made by the user are kept.
+    /// Nothing happens before the game was started once and BepInEx created the file, run it again after that.
+    pub fn apply_presets(&self, presets: &BTreeMap<String, BTreeMap<String, String>>) -> Result<PresetResult, InstallerError> {
+        let mut result = PresetResult::default();
+        // the presets are BepInEx settings, other loaders have nothing to apply them to
+        if presets.is_empty() || self.loader.kind() != LoaderKind::BepInEx {
+            return Ok(result);
+        }
+        let cfg_path = Path::new(&self.path).join(BEPINEX_CFG);
+        if !cfg_path.exists() {
+            result.pending = true;
+            return Ok(result);
+        }
 
-        // remove zip file from temp folder
-        std::fs::remove_file(&bepinex_zip_path).map_err(InstallerError::IoError)?;
+        let mut file = CfgFile::load(&cfg_path)?;
+        let mut records = Records::load(&self.path);
+        // values written by earlier presets stay ours, even when they are not part of these presets
+        let mut written = records.bepinex_cfg.clone();
+        for (section, values) in presets {
+            for (key, value) in values {
+                let name = format!("[{}] {}", section, key);
+                let entry = match file.get(section, key) {
+                    Some(entry) => entry,
+                    None => {
+                        result.missing.push(name);
+                        continue;
+                    }
+                };
+                let previous = records.bepinex_cfg.get(section).and_then(|x| x.get(key));
+                if entry.value == *value {
+                    // already set, by us or by hand
+                } else if entry.default.as_ref() == Some(&entry.value) || previous == Some(&entry.value) {
+                    if let Err(err) = file.set(section, key, value) {
+                        self.observer.warning(&format!("Skipped {}: {}", name, err));
+                        continue;
+                    }
+                    result.applied.push(name);
+                } else {
+                    // changed by hand, the value is no longer ours to replace
+                    if let Some(values) = written.get_mut(section) {
+                        values.remove(key);
+                    }
+                    written.retain(|_, x| !x.is_empty());
+                    result.kept.push(name);
+                    continue;
+                }
+                written.entry(section.clone()).or_default().insert(key.clone(), value.clone());
+            }
+        }
+
+        if !result.applied.is_empty() {
+            file.save(&cfg_path)?;
+        }
+        records.bepinex_cfg = written;
+        records.save(&self.path)?;
+
+        return Ok(result);
+    }
+
+    /// Version of the installed loader, `None` when it is missing or was not installed by us.
+    pub fn loader_version(&self) -> Option<String> {
+        if !self.is_installed() {
+            return None;
+        }
+
+        return Records::load(&self.path).loader;
+    }
+
+    /// Removes a plugin installed by this installer, together with every file it brought along.
+    pub fn uninstall_plugin(&self, name: &str) -> Result<PluginRecord, InstallerError> {
+        let mut records = Records::load(&self.path);
+        let record = match records.find(name) {
+            Some(record) => record.clone(),
+            None => return Err(InstallerError::ValidationError(format!("{} is not installed", name))),
+        };
 
-        // move contents of temp folder to game folder
-        let temp_path = Path::new(&self.path).join("temp");
-        // move contents of temp folder to game folder which means back one folder
         let game_path = Path::new(&self.path);
+        let mut transaction = Transaction::new(game_path, &game_path.join("temp")).map_err(InstallerError::io(game_path.join("temp")))?;
+        for file in record.current_files() {
+            transaction.remove(Path::new(&file));
+        }
+        transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        records.plugins.retain(|x| x.name != record.name);
+        records.save(&self.path)?;
+
+        return Ok(record);
+    }
+
+    /// Moves the files of a plugin out of the plugins folder or back into it.
+    pub fn set_plugin_enabled(&self, name: &str, enabled: bool) -> Result<PluginRecord, InstallerError> {
+        let mut records = Records::load(&self.path);
+        let record = match records.find(name) {
+            Some(record) => record.clone(),
+            None => return Err(InstallerError::ValidationError(format!("{} is not installed", name))),
+        };
+
+        let game_path = Path::new(&self.path);
+        let temp_path = game_path.join("temp");
+        let mut transaction = Transaction::new(game_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+        let record = Installer::stage_plugin_state(&mut transaction, game_path, &record, enabled)?;
+        transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        records.insert(record.clone());
+        records.save(&self.path)?;
 
-        for entry in std::fs::read_dir(&temp_path).map_err(InstallerError::IoError)? {
-            let entry = entry.map_err(InstallerError::IoError)?;
-            let path = entry.path();
-            let file_name = path.file_name().unwrap().to_str().unwrap();
-            let game_path = game_path.join(file_name);
-            std::fs::rename(&path, &game_path).map_err(InstallerError::IoError)?;
+        return Ok(record);
+    }
+
+    // stages moving the files of a plugin to where `enabled` wants them, returns the updated record
+    pub(crate) fn stage_plugin_state(transaction: &mut Transaction, game_path: &Path, record: &PluginRecord, enabled: bool) -> Result<PluginRecord, InstallerError> {
+        let mut updated = record.clone();
+        updated.disabled = !enabled;
+        if record.disabled == updated.disabled {
+            return Ok(updated);
         }
 
+        for (from, to) in record.current_files().iter().zip(updated.current_files()) {
+            // files deleted by hand are skipped
+            if !game_path.join(from).is_file() {
+                continue;
+            }
+            transaction.move_file(Path::new(from), Path::new(&to)).map_err(InstallerError::io(game_path.join(from)))?;
+        }
 
-        // delete temp folder
-        std::fs::remove_dir_all(&temp_path).map_err(InstallerError::IoError)?;
+        return Ok(updated);
+    }
+
+    /// Removes the loader and every plugin from the game folder, snapshots are kept.
+    pub fn uninstall(&self) -> Result<(), InstallerError> {
+        self.loader.uninstall(Path::new(&self.path))?;
+
+        // the records are kept in the BepInEx folder, other loaders leave them behind
+        let records_path = Records::records_path(&self.path);
+        if records_path.exists() {
+            std::fs::remove_file(&records_path).map_err(InstallerError::io(&records_path))?;
+            let _ = std::fs::remove_dir(Path::new(&self.path).join("BepInEx"));
+        }
 
         return Ok(());
     }
 
-    fn has_ran(&self) -> bool {
-        // check if 'bepinex' folder contains 'interop' folder
-        let bepinex_path = Path::new(&self.path).join("BepInEx");
-        if !bepinex_path.exists() {
-            return false;
+    pub fn loader_state(&self) -> LoaderState {
+        if !self.is_installed() {
+            return LoaderState::Missing;
+        }
+
+        let game_path = Path::new(&self.path);
+        let config = std::fs::read_to_string(game_path.join(DOORSTOP_CONFIG)).unwrap_or_default();
+        if game_path.join(self.loader.proxy_dll()).exists() && Installer::doorstop_enabled(&config) != Some(false) {
+            return LoaderState::Enabled;
+        }
+
+        return LoaderState::Disabled;
+    }
+
+    /// Turns the loader on or off without removing it, the game starts unmodded while it is off.
+    pub fn set_loader_enabled(&self, enabled: bool) -> Result<(), InstallerError> {
+        if !self.is_installed() {
+            return Err(InstallerError::ValidationError(format!("{} is not installed", self.loader.name())));
+        }
+
+        let game_path = Path::new(&self.path);
+        let temp_path = game_path.join("temp");
+        let mut transaction = Transaction::new(game_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+
+        // rename the proxy dll, the game only loads it under its real name
+        let proxy_dll = self.loader.proxy_dll().to_string();
+        let disabled = format!("{}.disabled", proxy_dll);
+        let (from, to) = if enabled { (&disabled, &proxy_dll) } else { (&proxy_dll, &disabled) };
+        if game_path.join(from).exists() {
+            transaction.move_file(Path::new(from), Path::new(to)).map_err(InstallerError::io(game_path.join(from)))?;
+        } else if enabled && !game_path.join(&proxy_dll).exists() {
+            return Err(InstallerError::ValidationError(format!("{} is missing, reinstall {} to get it back", proxy_dll, self.loader.name())));
+        }
+
+        // doorstop also has its own switch, it belongs to BepInEx
+        let config_path = game_path.join(DOORSTOP_CONFIG);
+        if config_path.exists() && self.loader.kind() == LoaderKind::BepInEx {
+            let config = std::fs::read_to_string(&config_path).map_err(InstallerError::io(&config_path))?;
+            let staged = transaction.staging_path().join(DOORSTOP_CONFIG);
+            std::fs::write(&staged, Installer::set_doorstop_enabled(&config, enabled)).map_err(InstallerError::io(&staged))?;
+        }
+
+        transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        return Ok(());
+    }
+
+    // value of `enabled` in doorstop_config.ini
+    pub(crate) fn doorstop_enabled(config: &str) -> Option<bool> {
+        for line in config.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == "enabled" {
+                    return Some(value.trim().eq_ignore_ascii_case("true"));
+                }
+            }
+        }
+
+        return None;
+    }
+
+    // rewrites the `enabled` line and keeps everything else, including the line endings
+    fn set_doorstop_enabled(config: &str, enabled: bool) -> String {
+        let mut found = false;
+        let mut lines = config
+            .split_inclusive('\n')
+            .map(|line| match line.split_once('=') {
+                Some((key, value)) if key.trim() == "enabled" && !found => {
+                    found = true;
+                    let ending = &value[value.trim_end_matches(['\r', '\n']).len()..];
+                    format!("{}= {}{}", key, enabled, ending)
+                }
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        // older configs without the switch get it at the top of the general section
+        if !found {
+            let line = format!("enabled = {}\n", enabled);
+            match lines.iter().position(|x| x.trim() == "[General]") {
+                Some(index) => lines.insert(index + 1, line),
+                None => lines.insert(0, format!("[General]\n{}", line)),
+            }
+        }
+
+        return lines.concat();
+    }
+
+    /// Whether the loader generated the interop assemblies, which happens on the first launch after installing.
+    /// Plugins are not loaded before that.
+    pub fn has_ran(&self) -> bool {
+        let interop_path = Path::new(&self.path).join(self.loader.interop_path());
+
+        // a launch that was cut short can leave it empty
+        return std::fs::read_dir(&interop_path).map(|mut x| x.next().is_some()).unwrap_or(false);
+    }
+
+    /// Starts the game through Steam, waits until the loader generated the interop assemblies and loaded
+    /// the plugins, then closes the game again.
+    pub fn first_run(&self, timeout: Duration) -> Result<(), InstallerError> {
+        match self.loader_state() {
+            LoaderState::Missing => return Err(InstallerError::ValidationError(format!("{} is not installed", self.loader.name()))),
+            LoaderState::Disabled => return Err(InstallerError::ValidationError(format!("{} is disabled, enable it first", self.loader.name()))),
+            LoaderState::Enabled => {}
         }
-        // check if 'bepinex' folder contains 'interop' folder
-        let bepinex_interop_path = bepinex_path.join("interop");
-        if !bepinex_interop_path.exists() {
+
+        let launcher = Launcher::new(&self.path);
+        let log_path = Path::new(&self.path).join(self.loader.log_path());
+        let started = SystemTime::now();
+        self.observer.step_started(Step::FirstRun);
+        launcher.launch()?;
+
+        // steam takes a while to start the game, it only counts as closed once it was seen running
+        let mut seen_running = false;
+        loop {
+            std::thread::sleep(Duration::from_secs(2));
+            if self.has_ran() && Installer::log_finished(&log_path, started, self.loader.startup_complete()) {
+                return launcher.close();
+            }
+
+            let running = launcher.is_running();
+            if seen_running && !running {
+                return Err(InstallerError::ValidationError(format!("The game closed before {} finished, see {}", self.loader.name(), log_path.display())));
+            }
+            seen_running |= running;
+
+            if started.elapsed().unwrap_or_default() >= timeout {
+                return Err(InstallerError::ValidationError(format!(
+                    "{} did not finish within {} seconds, see {}",
+                    self.loader.name(),
+                    timeout.as_secs(),
+                    log_path.display()
+                )));
+            }
+        }
+    }
+
+    // the log of this launch, not the one left over from the last
+    fn log_finished(log_path: &Path, since: SystemTime, finished: &str) -> bool {
+        let modified = std::fs::metadata(log_path).and_then(|x| x.modified());
+        if !matches!(modified, Ok(modified) if modified >= since) {
             return false;
         }
 
-        return true;
+        return std::fs::read_to_string(log_path).map(|x| x.contains(finished)).unwrap_or(false);
     }
 
-    fn is_installed(&self) -> bool {
-        // check if 'BepInEx' folder exists
-        let bepinex_path = Path::new(&self.path).join("BepInEx");
-        // if it does not exist then bepinex is not installed
-        if !bepinex_path.exists() {
-            return false; // bepinex is not installed
+    /// Starts the game through Steam, or the executable with `direct`. Vanilla and profile launches change
+    /// the loader and the plugins for the launch only, this waits for the game to exit and puts them back.
+    pub fn launch(&self, mode: &LaunchMode, direct: bool) -> Result<LaunchRestore, InstallerError> {
+        let launcher = Launcher::new(&self.path);
+        if launcher.is_running() {
+            return Err(InstallerError::ValidationError("The game is already running".to_string()));
         }
+        // an earlier launch was interrupted before the game exited
+        self.restore_launch()?;
 
-        // check if 'BepInEx' folder contains 'core' folder
-        let bepinex_core_path = bepinex_path.join("core");
-        // if it does not exist then bepinex is not installed
-        if !bepinex_core_path.exists() {
-            return false; // bepinex is not installed
+        let restore = self.prepare_launch(mode)?;
+        let launched = if direct { launcher.launch_direct() } else { launcher.launch() };
+        if let Err(err) = launched {
+            self.restore_launch()?;
+            return Err(err);
         }
+        if restore.is_empty() {
+            return Ok(restore);
+        }
+
+        self.observer.step_started(Step::WaitForExit);
+        let waited = launcher.wait_for_exit(Duration::from_secs(LAUNCH_TIMEOUT));
+        self.restore_launch()?;
+        waited?;
 
-        // check if 'BepInEx' folder contains 'plugins' folder
-        let bepinex_plugins_path = bepinex_path.join("plugins");
-        // if it does not exist then bepinex is not installed
-        if !bepinex_plugins_path.exists() {
-            return false; // bepinex is not installed
+        return Ok(restore);
+    }
+
+    // switches the loader and profile for the launch, what to put back is saved before anything changes
+    fn prepare_launch(&self, mode: &LaunchMode) -> Result<LaunchRestore, InstallerError> {
+        let mut restore = LaunchRestore::default();
+        let state = self.loader_state();
+        let enabled = *mode != LaunchMode::Vanilla;
+        if state == LoaderState::Missing && enabled {
+            return Err(InstallerError::ValidationError(format!("{} is not installed, run install first", self.loader.name())));
+        }
+        if state != LoaderState::Missing && (state == LoaderState::Enabled) != enabled {
+            restore.loader_enabled = Some(state == LoaderState::Enabled);
         }
 
-        // check if 'BepInEx' folder contains 'config' folder
-        let bepinex_config_path = bepinex_path.join("config");
-        // if it does not exist then bepinex is not installed
-        if !bepinex_config_path.exists() {
-            return false; // bepinex is not installed
+        let profiles = Profiles::new(&self.path);
+        if let LaunchMode::Profile(name) = mode {
+            profiles.load(name)?;
+            let active = profiles.active().filter(|x| profiles.load(x).is_ok());
+            match active {
+                Some(active) if active == *name => {}
+                Some(active) => restore.profile = Some(active),
+                None => {
+                    if profiles.load(LAUNCH_PROFILE).is_ok() {
+                        return Err(InstallerError::ValidationError(format!("Profile {} is in the way, delete or rename it first", LAUNCH_PROFILE)));
+                    }
+                    profiles.save(LAUNCH_PROFILE)?;
+                    restore.profile = Some(LAUNCH_PROFILE.to_string());
+                    restore.temporary_profile = true;
+                }
+            }
         }
+        if restore.is_empty() {
+            return Ok(restore);
+        }
+
+        let mut records = Records::load(&self.path);
+        records.launch = Some(restore.clone());
+        records.save(&self.path)?;
+
+        let applied = match mode {
+            LaunchMode::Profile(name) if restore.profile.is_some() => profiles.switch(name).map(|_| ()),
+            _ => Ok(()),
+        };
+        let applied = applied.and_then(|_| match restore.loader_enabled {
+            Some(_) => self.set_loader_enabled(enabled),
+            None => Ok(()),
+        });
+        if let Err(err) = applied {
+            self.restore_launch()?;
+            return Err(err);
+        }
+
+        return Ok(restore);
+    }
+
+    /// Puts back what an unfinished launch changed, returns what that was.
+    pub fn restore_launch(&self) -> Result<Option<LaunchRestore>, InstallerError> {
+        let restore = match Records::load(&self.path).launch {
+            Some(restore) => restore,
+            None => return Ok(None),
+        };
+
+        if let Some(profile) = &restore.profile {
+            let profiles = Profiles::new(&self.path);
+            if profiles.active().as_deref() != Some(profile.as_str()) {
+                profiles.switch(profile)?;
+            }
+            if restore.temporary_profile {
+                profiles.delete(profile)?;
+            }
+        }
+        if let Some(enabled) = restore.loader_enabled {
+            if (self.loader_state() == LoaderState::Enabled) != enabled {
+                self.set_loader_enabled(enabled)?;
+            }
+        }
+
+        let mut records = Records::load(&self.path);
+        records.launch = None;
+        records.save(&self.path)?;
+
+        return Ok(Some(restore));
+    }
+
+    pub fn is_installed(&self) -> bool {
+        return self.loader.is_installed(Path::new(&self.path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_finds_a_changed_file_and_repair_puts_it_back() {
+        let root = std::env::temp_dir().join(format!("bepinex_installer-repair-{}", std::process::id()));
+        let game = root.join("game");
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["BepInEx/core", "BepInEx/plugins", "BepInEx/config"] {
+            std::fs::create_dir_all(game.join(dir)).unwrap();
+        }
+        std::fs::write(game.join("winhttp.dll"), "proxy").unwrap();
+        std::fs::write(game.join("BepInEx/plugins/Mod.dll"), "original").unwrap();
+
+        let path = game.to_string_lossy().to_string();
+        let records = Records {
+            loader_files: Installer::hash_files(&path, &[game.join("winhttp.dll")]).unwrap(),
+            plugins: vec![PluginRecord {
+                name: "Mod".to_string(),
+                version: "1.0.0".to_string(),
+                files: vec!["BepInEx/plugins/Mod.dll".to_string()],
+                hashes: Installer::hash_files(&path, &[game.join("BepInEx/plugins/Mod.dll")]).unwrap(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        records.save(&path).unwrap();
+        std::fs::write(game.join("BepInEx/plugins/Mod.dll"), "changed").unwrap();
+
+        let installer = Installer::new(&path);
+        let verified = installer.verify().unwrap();
+        assert_eq!(verified.issues.len(), 1);
+        assert_eq!(verified.issues[0].path, "BepInEx/plugins/Mod.dll");
+        assert_eq!(verified.issues[0].owner, "Mod");
+        assert_eq!(verified.issues[0].problem, FileProblem::Changed);
+
+        // repair stages the downloaded copy of every file that differs from its hash
+        let download = root.join("download");
+        std::fs::create_dir_all(&download).unwrap();
+        std::fs::write(download.join("Mod.dll"), "original").unwrap();
+        std::fs::write(download.join("winhttp.dll"), "proxy").unwrap();
+        let mut transaction = Transaction::new(&game, &game.join("temp")).unwrap();
+        let mut restored = Vec::new();
+        let hash = records.plugins[0].hashes["BepInEx/plugins/Mod.dll"].clone();
+        installer.stage_restore(&mut transaction, &download.join("Mod.dll"), "BepInEx/plugins/Mod.dll", Some(&hash), "Mod", &mut restored).unwrap();
+        let hash = records.loader_files["winhttp.dll"].clone();
+        installer.stage_restore(&mut transaction, &download.join("winhttp.dll"), "winhttp.dll", Some(&hash), "BepInEx", &mut restored).unwrap();
+        transaction.commit().unwrap();
+
+        assert_eq!(restored.len(), 1);
+        assert_eq!(std::fs::read_to_string(game.join("BepInEx/plugins/Mod.dll")).unwrap(), "original");
+        assert!(installer.verify().unwrap().issues.is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn set_doorstop_enabled_keeps_the_rest_of_the_config() {
+        let config = "[General]\r\n# comment\r\nenabled = true\r\ntarget_assembly = BepInEx\\core\\BepInEx.Unity.IL2CPP.dll\r\n";
+        let disabled = Installer::set_doorstop_enabled(config, false);
+        assert_eq!(disabled, "[General]\r\n# comment\r\nenabled = false\r\ntarget_assembly = BepInEx\\core\\BepInEx.Unity.IL2CPP.dll\r\n");
+        assert_eq!(Installer::doorstop_enabled(&disabled), Some(false));
+        assert_eq!(Installer::set_doorstop_enabled(&disabled, true), config);
+
+        // older configs without the switch get it below [General]
+        assert_eq!(Installer::set_doorstop_enabled("[General]\nredirect_output_log = false\n", false), "[General]\nenabled = false\nredirect_output_log = false\n");
+        assert_eq!(Installer::set_doorstop_enabled("", true), "[General]\nenabled = true\n");
+    }
+
+    #[test]
+    fn set_loader_enabled_renames_the_proxy_dll_and_toggles_doorstop() {
+        let root = std::env::temp_dir().join(format!("bepinex_installer-loader-state-{}", std::process::id()));
+        let game = root.join("game");
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["BepInEx/core", "BepInEx/plugins", "BepInEx/config"] {
+            std::fs::create_dir_all(game.join(dir)).unwrap();
+        }
+        let path = game.to_string_lossy().to_string();
+        let installer = Installer::new(&path);
+        let proxy_dll = installer.loader().proxy_dll().to_string();
+        let disabled = format!("{}.disabled", proxy_dll);
+        std::fs::write(game.join(&proxy_dll), "proxy").unwrap();
+        std::fs::write(game.join(DOORSTOP_CONFIG), "[General]\nenabled = true\n").unwrap();
+        assert_eq!(installer.loader_state(), LoaderState::Enabled);
+
+        installer.set_loader_enabled(false).unwrap();
+        assert!(!game.join(&proxy_dll).exists());
+        assert_eq!(std::fs::read_to_string(game.join(&disabled)).unwrap(), "proxy");
+        assert_eq!(std::fs::read_to_string(game.join(DOORSTOP_CONFIG)).unwrap(), "[General]\nenabled = false\n");
+        assert_eq!(installer.loader_state(), LoaderState::Disabled);
+
+        installer.set_loader_enabled(true).unwrap();
+        assert!(!game.join(&disabled).exists());
+        assert_eq!(std::fs::read_to_string(game.join(&proxy_dll)).unwrap(), "proxy");
+        assert_eq!(std::fs::read_to_string(game.join(DOORSTOP_CONFIG)).unwrap(), "[General]\nenabled = true\n");
+        assert_eq!(installer.loader_state(), LoaderState::Enabled);
+
+        // doorstop switched off by hand disables the loader too
+        std::fs::write(game.join(DOORSTOP_CONFIG), "[General]\nenabled = false\n").unwrap();
+        assert_eq!(installer.loader_state(), LoaderState::Disabled);
+
+        std::fs::remove_dir_all(&game).unwrap();
+        assert_eq!(installer.loader_state(), LoaderState::Missing);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn apply_presets_only_replaces_defaults_and_our_own_values() {
+        let game = std::env::temp_dir().join(format!("bepinex_installer-presets-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&game);
+        let path = game.to_string_lossy().to_string();
+        let installer = Installer::new(&path);
+        let mut presets: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        for (section, key, value) in [
+            ("Logging.Console", "Enabled", "true"),
+            ("Chainloader", "HideManagerGameObject", "false"),
+            ("Logging.Disk", "WriteUnityLog", "false"),
+            ("Preloader", "Missing", "true"),
+        ] {
+            presets.entry(section.to_string()).or_default().insert(key.to_string(), value.to_string());
+        }
+        assert!(installer.apply_presets(&presets).unwrap().pending);
+
+        // Enabled still has its default, HideManagerGameObject has the value we wrote last time
+        // and WriteUnityLog was changed by hand since
+        let setting = |section: &str, key: &str, value: &str| {
+            format!("[{}]\r\n\r\n# Setting type: Boolean\r\n# Default value: false\r\n{} = {}\r\n\r\n", section, key, value)
+        };
+        let cfg = [
+            setting("Chainloader", "HideManagerGameObject", "true"),
+            setting("Logging.Console", "Enabled", "false"),
+            setting("Logging.Disk", "WriteUnityLog", "true"),
+        ]
+        .concat();
+        std::fs::create_dir_all(game.join("BepInEx/config")).unwrap();
+        std::fs::write(game.join(BEPINEX_CFG), cfg).unwrap();
+        let mut records = Records::default();
+        records.bepinex_cfg.entry("Chainloader".to_string()).or_default().insert("HideManagerGameObject".to_string(), "true".to_string());
+        records.bepinex_cfg.entry("Logging.Disk".to_string()).or_default().insert("WriteUnityLog".to_string(), "false".to_string());
+        records.save(&path).unwrap();
+
+        let result = installer.apply_presets(&presets).unwrap();
+        assert_eq!(result.applied, vec!["[Chainloader] HideManagerGameObject", "[Logging.Console] Enabled"]);
+        assert_eq!(result.kept, vec!["[Logging.Disk] WriteUnityLog"]);
+        assert_eq!(result.missing, vec!["[Preloader] Missing"]);
+        assert!(!result.pending);
+        let file = CfgFile::load(&game.join(BEPINEX_CFG)).unwrap();
+        assert_eq!(file.get("Chainloader", "HideManagerGameObject").unwrap().value, "false");
+        assert_eq!(file.get("Logging.Console", "Enabled").unwrap().value, "true");
+        assert_eq!(file.get("Logging.Disk", "WriteUnityLog").unwrap().value, "true");
 
-        // bepinex is installed
-        return true;
+        // the kept value is no longer ours, the applied ones are
+        let written = Records::load(&path).bepinex_cfg;
+        assert_eq!(written["Chainloader"]["HideManagerGameObject"], "false");
+        assert_eq!(written["Logging.Console"]["Enabled"], "true");
+        assert!(!written.contains_key("Logging.Disk"));
+        assert!(installer.apply_presets(&presets).unwrap().applied.is_empty());
+        std::fs::remove_dir_all(&game).unwrap();
     }
 }
\ No newline at end of file
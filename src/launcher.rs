@@ -0,0 +1,157 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::config::GAME_ID;
+use crate::error::InstallerError;
+
+/// Starts and stops the game. It is started through Steam so it gets the overlay and, on Linux, Proton.
+pub struct Launcher {
+    path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchMode {
+    Modded,
+    // the loader is disabled until the game exits
+    Vanilla,
+    // the plugins and config files of a profile until the game exits
+    Profile(String),
+}
+
+impl Launcher {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn launch(&self) -> Result<(), InstallerError> {
+        let url = format!("steam://rungameid/{}", GAME_ID);
+        return Launcher::open(&url)
+            .map(|_| ())
+            .map_err(|x| InstallerError::SteamError(format!("Could not open {}: {}", url, x)));
+    }
+
+    /// Starts the executable in the game folder without Steam.
+    #[cfg(target_os = "windows")]
+    pub fn launch_direct(&self) -> Result<(), InstallerError> {
+        let executable = match self.executable() {
+            Some(executable) => Path::new(&self.path).join(executable),
+            None => return Err(InstallerError::ValidationError(format!("No game executable found in {}", self.path))),
+        };
+        // the game looks for its data next to the working directory
+        return Command::new(&executable)
+            .current_dir(&self.path)
+            .spawn()
+            .map(|_| ())
+            .map_err(InstallerError::io(&executable));
+    }
+
+    // the windows executable needs proton, which only steam sets up
+    #[cfg(target_os = "linux")]
+    pub fn launch_direct(&self) -> Result<(), InstallerError> {
+        return Err(InstallerError::ValidationError("Starting the game directly only works on Windows, leave out --direct to start it through Steam".to_string()));
+    }
+
+    /// Waits until the game was seen running and has exited again.
+    /// Gives up when it does not show up within `start_timeout`.
+    pub fn wait_for_exit(&self, start_timeout: Duration) -> Result<(), InstallerError> {
+        let started = Instant::now();
+        let mut seen_running = false;
+        loop {
+            std::thread::sleep(Duration::from_secs(2));
+            let running = self.is_running();
+            if seen_running && !running {
+                return Ok(());
+            }
+            seen_running |= running;
+
+            if !seen_running && started.elapsed() >= start_timeout {
+                return Err(InstallerError::ValidationError(format!("The game did not start within {} seconds", start_timeout.as_secs())));
+            }
+        }
+    }
+
+    /// File name of the game executable, the crash handler next to it does not count.
+    pub fn executable(&self) -> Option<String> {
+        let entries = std::fs::read_dir(Path::new(&self.path)).ok()?;
+        return entries
+            .filter_map(|x| x.ok())
+            .map(|x| x.file_name().to_string_lossy().to_string())
+            .find(|x| x.to_lowercase().ends_with(".exe") && !x.eq_ignore_ascii_case("UnityCrashHandler64.exe"));
+    }
+
+    pub fn is_running(&self) -> bool {
+        return match self.executable() {
+            Some(executable) => Launcher::find_process(&executable),
+            None => false,
+        };
+    }
+
+    pub fn close(&self) -> Result<(), InstallerError> {
+        let executable = match self.executable() {
+            Some(executable) => executable,
+            None => return Err(InstallerError::ValidationError(format!("No game executable found in {}", self.path))),
+        };
+        // nothing to do when it already exited
+        if !Launcher::kill_process(&executable) && Launcher::find_process(&executable) {
+            return Err(InstallerError::ValidationError(format!("Could not close {}, close the game by hand", executable)));
+        }
+
+        return Ok(());
+    }
+
+    #[cfg(target_os = "windows")]
+    fn open(url: &str) -> std::io::Result<std::process::Child> {
+        // the empty argument is the window title
+        return Command::new("cmd").args(["/C", "start", "", url]).spawn();
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open(url: &str) -> std::io::Result<std::process::Child> {
+        return Command::new("xdg-open").arg(url).spawn();
+    }
+
+    #[cfg(target_os = "windows")]
+    fn find_process(executable: &str) -> bool {
+        let output = Command::new("tasklist").args(["/FI", &format!("IMAGENAME eq {}", executable), "/NH"]).output();
+        return match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).to_lowercase().contains(&executable.to_lowercase()),
+            Err(_) => false,
+        };
+    }
+
+    // proton runs the windows executable, its path shows up in the command line
+    #[cfg(target_os = "linux")]
+    fn find_process(executable: &str) -> bool {
+        let pattern = Launcher::process_pattern(executable);
+        return Command::new("pgrep").args(["-f", &pattern]).output().map(|x| x.status.success()).unwrap_or(false);
+    }
+
+    #[cfg(target_os = "windows")]
+    fn kill_process(executable: &str) -> bool {
+        return Command::new("taskkill").args(["/IM", executable, "/F"]).output().map(|x| x.status.success()).unwrap_or(false);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn kill_process(executable: &str) -> bool {
+        let pattern = Launcher::process_pattern(executable);
+        return Command::new("pkill").args(["-f", &pattern]).output().map(|x| x.status.success()).unwrap_or(false);
+    }
+
+    // the executable at the start of a path, so a shell that merely mentions it does not match
+    #[cfg(target_os = "linux")]
+    fn process_pattern(executable: &str) -> String {
+        let mut escaped = String::new();
+        for character in executable.chars() {
+            if "\\.+*?()|[]{}^$".contains(character) {
+                escaped.push('\\');
+            }
+            escaped.push(character);
+        }
+
+        return format!("(^|[/\\\\]){}( |$)", escaped);
+    }
+}
@@ -0,0 +1,94 @@
+//! Installs BepInEx and the SoulstoneAddons plugins into Soulstone Survivors.
+//!
+//! The console installer is a thin wrapper around this library, other tools can
+//! use it to find the game, resolve plugins and install or uninstall them:
+//!
+//! ```no_run
+//! use bepinex_installer::{Installer, InstallerError, Plugins, Steam, GAME_ID};
+//!
+//! # fn main() -> Result<(), InstallerError> {
+//! let game = Steam::find_game(GAME_ID)?;
+//! let installer = Installer::new(&game.path);
+//! if !installer.is_installed() {
+//!     installer.install_loader()?;
+//! }
+//! let plugins = Plugins::get_plugins()?;
+//! installer.install_plugins(&plugins)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every function fails with an [`InstallerError`] that says what it was working on,
+//! [`InstallerError::suggestion`] tells the user what to do about it.
+//!
+//! Requests use the proxy, GitHub token and cache of the applied [`Settings`], apply
+//! them once before anything else with [`Settings::apply`].
+//!
+//! Progress, warnings and prompts go to an [`Observer`], set one with
+//! [`Installer::set_observer`] to show them:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use bepinex_installer::{Installer, Observer, Plugin, PluginStatus};
+//!
+//! struct Log;
+//!
+//! impl Observer for Log {
+//!     fn plugin_status(&self, _index: usize, plugin: &Plugin, status: PluginStatus) {
+//!         if let PluginStatus::Installed(version) = status {
+//!             println!("{} {} installed", plugin.name, version);
+//!         }
+//!     }
+//! }
+//!
+//! let mut installer = Installer::new("C:/Games/Soulstone Survivors");
+//! installer.set_observer(Arc::new(Log));
+//! ```
+
+// every function ends in an explicit `return`
+#![allow(clippy::needless_return)]
+
+pub mod backup;
+pub mod cfg;
+pub mod config;
+pub mod doctor;
+mod download;
+pub mod error;
+mod extract;
+mod github_releases;
+mod github_repositories;
+mod hash;
+mod http;
+pub mod installer;
+pub mod launcher;
+pub mod loader;
+pub mod logs;
+pub mod observer;
+pub mod plugin;
+pub mod plugins;
+pub mod profiles;
+pub mod records;
+pub mod settings;
+pub mod steam;
+mod transaction;
+pub mod updates;
+
+pub use crate::backup::{Backup, RestoreResult, Snapshot};
+pub use crate::cfg::{Acceptable, CfgEntry, CfgFile};
+pub use crate::config::GAME_ID;
+pub use crate::doctor::{Check, CheckStatus, Diagnosis, Doctor};
+pub use crate::error::InstallerError;
+pub use crate::extract::RejectedEntry;
+pub use crate::github_releases::{Asset, Release};
+pub use crate::installer::{FileIssue, FileProblem, InstallResult, Installer, LoaderConflict, LoaderState, PresetResult, RepairResult, VerifyResult};
+pub use crate::launcher::{LaunchMode, Launcher};
+pub use crate::loader::{BepInEx, LoaderKind, MelonLoader, ModLoader};
+pub use crate::logs::{LogEntry, LogLevel, LogProblem, Logs, ProblemKind};
+pub use crate::observer::{Observer, PluginStatus, SilentObserver, Step};
+pub use crate::plugin::{Plugin, PluginDownload};
+pub use crate::plugins::Plugins;
+pub use crate::profiles::{Profile, Profiles, SwitchResult};
+pub use crate::records::{LaunchRestore, PluginRecord, Records};
+pub use crate::settings::{Channel, Settings};
+pub use crate::steam::{Steam, SteamGame};
+pub use crate::updates::{GameUpdate, GameUpdates, PluginUpdate};
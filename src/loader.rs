@@ -0,0 +1,285 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::config::{BEPINEX_URL, BEPINEX_VERSION, MELONLOADER_URL, MELONLOADER_VERSION};
+use crate::download::Downloader;
+use crate::error::InstallerError;
+use crate::extract::{Extractor, RejectedEntry};
+use crate::settings::Settings;
+use crate::transaction::Transaction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoaderKind {
+    BepInEx,
+    MelonLoader,
+}
+
+/// A mod loader the installer can set up: where it comes from, what it puts into the game folder
+/// and where the plugins it loads go.
+pub trait ModLoader: Send + Sync {
+    fn kind(&self) -> LoaderKind;
+
+    fn name(&self) -> &'static str;
+
+    // the version `url` points at
+    fn version(&self) -> &'static str;
+
+    fn url(&self) -> &'static str;
+
+    // everything the loader puts into the game folder, removed on uninstall
+    fn paths(&self) -> &'static [&'static str];
+
+    // the dll the game loads the loader through, renamed to `<name>.disabled` to turn the loader off
+    fn proxy_dll(&self) -> &'static str;
+
+    // the folder only this loader has, the loader counts as there when it exists
+    fn signature(&self) -> &'static str;
+
+    // the other files the loader puts into the game root, they are only its own next to `signature`
+    fn markers(&self) -> &'static [&'static str];
+
+    // checked when no hashes were recorded, the loader does not start without them
+    fn essential_files(&self) -> &'static [&'static str];
+
+    // plugins are installed into this folder, relative to the game folder
+    fn plugins_path(&self) -> &'static str;
+
+    // disabled plugins are kept here, the loader never looks there
+    fn disabled_path(&self) -> &'static str;
+
+    // plugin settings, profiles keep their own copy
+    fn config_path(&self) -> &'static str;
+
+    // generated from the game on the first launch, plugins are only loaded after that
+    fn interop_path(&self) -> &'static str;
+
+    fn log_path(&self) -> &'static str;
+
+    // logged once every plugin is loaded
+    fn startup_complete(&self) -> &'static str;
+
+    fn is_installed(&self, game_path: &Path) -> bool;
+
+    /// Downloads the loader and extracts it into `target`, laid out like the game folder.
+    /// The download is kept below `<game>/temp/downloads` so an interrupted one can be resumed.
+    fn download(&self, game_path: &Path, target: &Path, progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<Vec<RejectedEntry>, InstallerError> {
+        let downloads_path = Downloader::folder(game_path, self.name(), self.url());
+        let file_name = self.url().rsplit('/').next().unwrap_or("loader.zip");
+        let zip_path = downloads_path.join(file_name);
+        Downloader::download(self.url(), &zip_path, None, progress)?;
+
+        let archive = std::fs::File::open(&zip_path).map_err(InstallerError::io(&zip_path))?;
+        let mut archive = zip::ZipArchive::new(archive).map_err(InstallerError::zip(&zip_path))?;
+        let extracted = Extractor::extract(&mut archive, target).map_err(InstallerError::zip(&zip_path))?;
+        drop(archive);
+
+        // the download is no longer needed once it is extracted
+        std::fs::remove_file(&zip_path).map_err(InstallerError::io(&zip_path))?;
+        let _ = std::fs::remove_dir(&downloads_path);
+
+        return Ok(extracted.rejected);
+    }
+
+    /// Removes the loader and every plugin from the game folder.
+    fn uninstall(&self, game_path: &Path) -> Result<(), InstallerError> {
+        let mut transaction = Transaction::new(game_path, &game_path.join("temp")).map_err(InstallerError::io(game_path.join("temp")))?;
+        let mut files = Vec::new();
+        for entry in self.paths() {
+            Transaction::collect_files(game_path, Path::new(entry), &mut files).map_err(InstallerError::io(game_path.join(entry)))?;
+        }
+        for file in &files {
+            transaction.remove(file);
+        }
+        transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        // the folders are empty now
+        for entry in self.paths() {
+            let path = game_path.join(entry);
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path).map_err(InstallerError::io(&path))?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+pub struct BepInEx;
+
+pub struct MelonLoader;
+
+impl ModLoader for BepInEx {
+    fn kind(&self) -> LoaderKind {
+        return LoaderKind::BepInEx;
+    }
+
+    fn name(&self) -> &'static str {
+        return "BepInEx";
+    }
+
+    fn version(&self) -> &'static str {
+        return BEPINEX_VERSION;
+    }
+
+    fn url(&self) -> &'static str {
+        return BEPINEX_URL;
+    }
+
+    fn paths(&self) -> &'static [&'static str] {
+        return &["BepInEx", "dotnet", "winhttp.dll", "winhttp.dll.disabled", "doorstop_config.ini", ".doorstop_version", "changelog.txt"];
+    }
+
+    fn proxy_dll(&self) -> &'static str {
+        return "winhttp.dll";
+    }
+
+    fn signature(&self) -> &'static str {
+        return "BepInEx/core";
+    }
+
+    // doorstop files, other doorstop based loaders use the same names
+    fn markers(&self) -> &'static [&'static str] {
+        return &["winhttp.dll", "winhttp.dll.disabled", "doorstop_config.ini", ".doorstop_version"];
+    }
+
+    fn essential_files(&self) -> &'static [&'static str] {
+        return &["winhttp.dll", "doorstop_config.ini"];
+    }
+
+    fn plugins_path(&self) -> &'static str {
+        return "BepInEx/plugins";
+    }
+
+    fn disabled_path(&self) -> &'static str {
+        return "BepInEx/disabled";
+    }
+
+    fn config_path(&self) -> &'static str {
+        return "BepInEx/config";
+    }
+
+    fn interop_path(&self) -> &'static str {
+        return "BepInEx/interop";
+    }
+
+    fn log_path(&self) -> &'static str {
+        return "BepInEx/LogOutput.log";
+    }
+
+    fn startup_complete(&self) -> &'static str {
+        return "Chainloader startup complete";
+    }
+
+    fn is_installed(&self, game_path: &Path) -> bool {
+        // the zip brings core, plugins and config are created by the installer or the first launch
+        let bepinex_path = game_path.join("BepInEx");
+        return ["core", "plugins", "config"].iter().all(|x| bepinex_path.join(x).exists());
+    }
+}
+
+impl ModLoader for MelonLoader {
+    fn kind(&self) -> LoaderKind {
+        return LoaderKind::MelonLoader;
+    }
+
+    fn name(&self) -> &'static str {
+        return "MelonLoader";
+    }
+
+    fn version(&self) -> &'static str {
+        return MELONLOADER_VERSION;
+    }
+
+    fn url(&self) -> &'static str {
+        return MELONLOADER_URL;
+    }
+
+    fn paths(&self) -> &'static [&'static str] {
+        return &["MelonLoader", "Mods", "Plugins", "UserData", "UserLibs", "version.dll", "version.dll.disabled", "dobby.dll", "NOTICE.txt"];
+    }
+
+    fn proxy_dll(&self) -> &'static str {
+        return "version.dll";
+    }
+
+    fn signature(&self) -> &'static str {
+        return "MelonLoader";
+    }
+
+    fn markers(&self) -> &'static [&'static str] {
+        return &["version.dll", "version.dll.disabled", "dobby.dll"];
+    }
+
+    fn essential_files(&self) -> &'static [&'static str] {
+        return &["version.dll", "MelonLoader/net6/MelonLoader.dll"];
+    }
+
+    fn plugins_path(&self) -> &'static str {
+        return "Mods";
+    }
+
+    fn disabled_path(&self) -> &'static str {
+        return "MelonLoader/Disabled";
+    }
+
+    fn config_path(&self) -> &'static str {
+        return "UserData";
+    }
+
+    fn interop_path(&self) -> &'static str {
+        return "MelonLoader/Il2CppAssemblies";
+    }
+
+    fn log_path(&self) -> &'static str {
+        return "MelonLoader/Latest.log";
+    }
+
+    fn startup_complete(&self) -> &'static str {
+        return "Mods loaded";
+    }
+
+    fn is_installed(&self, game_path: &Path) -> bool {
+        return game_path.join("MelonLoader").join("net6").exists() && game_path.join("Mods").exists();
+    }
+}
+
+impl std::str::FromStr for LoaderKind {
+    type Err = InstallerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return match value.to_lowercase().as_str() {
+            "bepinex" => Ok(LoaderKind::BepInEx),
+            "melonloader" => Ok(LoaderKind::MelonLoader),
+            _ => Err(InstallerError::ValidationError(format!("Unknown mod loader {}, use bepinex or melonloader", value))),
+        };
+    }
+}
+
+impl LoaderKind {
+    pub const ALL: [LoaderKind; 2] = [LoaderKind::BepInEx, LoaderKind::MelonLoader];
+
+    pub fn loader(&self) -> &'static dyn ModLoader {
+        return match self {
+            LoaderKind::BepInEx => &BepInEx,
+            LoaderKind::MelonLoader => &MelonLoader,
+        };
+    }
+
+    /// The loader plugins are installed for: the configured one, else BepInEx.
+    /// Another loader found in the game folder is a conflict, see `Installer::conflicts`, not a reason to switch.
+    pub fn active() -> &'static dyn ModLoader {
+        return Settings::current().loader.unwrap_or(LoaderKind::BepInEx).loader();
+    }
+
+    /// The loader whose plugins folder holds `file`, with the rest of the path.
+    pub(crate) fn plugin_file(file: &str) -> Option<(&'static dyn ModLoader, &str)> {
+        for kind in LoaderKind::ALL {
+            let prefix = format!("{}/", kind.loader().plugins_path());
+            if let Some(start) = file.get(..prefix.len()).filter(|x| x.eq_ignore_ascii_case(&prefix)) {
+                return Some((kind.loader(), &file[start.len()..]));
+            }
+        }
+
+        return None;
+    }
+}
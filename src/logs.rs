@@ -0,0 +1,373 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::error::InstallerError;
+use crate::loader::{LoaderKind, ModLoader};
+use crate::records::Records;
+
+/// Reads the log of the mod loader, `BepInEx/LogOutput.log` or `MelonLoader/Latest.log`,
+/// and looks for the usual reasons plugins fail to load.
+pub struct Logs {
+    path: String,
+    loader: &'static dyn ModLoader,
+}
+
+// most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Fatal,
+    Error,
+    Warning,
+    Message,
+    Info,
+    Debug,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    // the plugin or part of the loader that logged it
+    pub source: String,
+    // stack traces and other continuation lines included
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    MissingDependency,
+    Incompatible,
+    // the game was updated after the interop assemblies were generated
+    OutdatedInterop,
+    // the plugin was built against types the game no longer has
+    TypeLoad,
+    MissingMember,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogProblem {
+    pub kind: ProblemKind,
+    // the responsible plugin, when it could be told
+    pub plugin: Option<String>,
+    pub message: String,
+    pub suggestion: String,
+}
+
+impl std::str::FromStr for LogLevel {
+    type Err = InstallerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return match value.trim().to_lowercase().as_str() {
+            "fatal" => Ok(LogLevel::Fatal),
+            "error" => Ok(LogLevel::Error),
+            "warning" => Ok(LogLevel::Warning),
+            "message" => Ok(LogLevel::Message),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(InstallerError::ValidationError(format!("Unknown log level {}, use fatal, error, warning, message, info or debug", value))),
+        };
+    }
+}
+
+impl LogEntry {
+    /// Parses the first line of an entry, `[Level  :  Source] message` from BepInEx or
+    /// `[12:00:00.000] [Source] message` from MelonLoader.
+    /// Returns `None` for continuation lines such as stack traces.
+    pub fn parse_line(line: &str) -> Option<LogEntry> {
+        return LogEntry::parse_bepinex_line(line).or_else(|| LogEntry::parse_melonloader_line(line));
+    }
+
+    fn parse_bepinex_line(line: &str) -> Option<LogEntry> {
+        let rest = line.strip_prefix('[')?;
+        let (header, message) = rest.split_once(']')?;
+        let (level, source) = header.split_once(':')?;
+
+        return Some(LogEntry {
+            level: level.parse().ok()?,
+            source: source.trim().to_string(),
+            message: message.trim_start().to_string(),
+        });
+    }
+
+    // errors and warnings are tagged after the source, everything else is a message
+    fn parse_melonloader_line(line: &str) -> Option<LogEntry> {
+        let (time, rest) = line.strip_prefix('[')?.split_once(']')?;
+        if time.len() < 8 || !time.chars().all(|x| x.is_ascii_digit() || x == ':' || x == '.') {
+            return None;
+        }
+        let rest = rest.trim_start();
+        let (mut source, mut message) = match rest.strip_prefix('[').and_then(|x| x.split_once(']')) {
+            Some((source, message)) => (source.trim(), message.trim_start()),
+            None => ("MelonLoader", rest),
+        };
+
+        let mut level = LogLevel::Message;
+        for (tag, tag_level) in [("ERROR", LogLevel::Error), ("WARNING", LogLevel::Warning)] {
+            if source.eq_ignore_ascii_case(tag) {
+                level = tag_level;
+                source = "MelonLoader";
+            } else if let Some(rest) = message.strip_prefix(&format!("[{}]", tag)) {
+                level = tag_level;
+                message = rest.trim_start();
+            }
+        }
+
+        return Some(LogEntry {
+            level,
+            source: source.to_string(),
+            message: message.to_string(),
+        });
+    }
+}
+
+impl Logs {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            loader: LoaderKind::active(),
+        }
+    }
+
+    // rewritten on every launch
+    pub fn log_path(&self) -> PathBuf {
+        return Path::new(&self.path).join(self.loader.log_path());
+    }
+
+    pub fn read(&self) -> Result<Vec<LogEntry>, InstallerError> {
+        let log_path = self.log_path();
+        if !log_path.exists() {
+            return Err(InstallerError::ValidationError(format!("{} does not exist, start the game once with {}", log_path.display(), self.loader.name())));
+        }
+        // the log is not always valid utf-8
+        let contents = std::fs::read(&log_path).map_err(InstallerError::io(&log_path))?;
+
+        return Ok(Logs::parse(&String::from_utf8_lossy(&contents)));
+    }
+
+    pub fn parse(contents: &str) -> Vec<LogEntry> {
+        let mut entries: Vec<LogEntry> = Vec::new();
+        for line in contents.lines() {
+            match LogEntry::parse_line(line) {
+                Some(entry) => entries.push(entry),
+                None => {
+                    // lines before the first entry belong to nothing
+                    if let Some(entry) = entries.last_mut() {
+                        entry.message.push('\n');
+                        entry.message.push_str(line);
+                    }
+                }
+            }
+        }
+
+        return entries;
+    }
+
+    /// Finds missing dependencies, outdated interop assemblies and plugins built for an older game.
+    pub fn analyze(&self, entries: &[LogEntry]) -> Vec<LogProblem> {
+        let records = Records::load(&self.path);
+        let mut problems: Vec<LogProblem> = Vec::new();
+        let mut add = |problem: LogProblem| {
+            if !problems.iter().any(|x| x.kind == problem.kind && x.plugin == problem.plugin) {
+                problems.push(problem);
+            }
+        };
+
+        if self.interop_outdated() {
+            add(LogProblem {
+                kind: ProblemKind::OutdatedInterop,
+                plugin: None,
+                message: format!("The game was updated after {} generated the interop assemblies", self.loader.name()),
+                suggestion: format!("Delete {} and start the game once so {} generates them again", self.loader.interop_path(), self.loader.name()),
+            });
+        }
+
+        // the chainloader logs `Loading [Name 1.0.0]` before it runs a plugin
+        let mut loading = None;
+        for entry in entries {
+            let first_line = entry.message.lines().next().unwrap_or_default();
+            if let Some(name) = first_line.strip_prefix("Loading [").and_then(Logs::bracketed) {
+                loading = Some(name);
+            }
+
+            if let Some(dependencies) = first_line.split_once("because it has missing dependencies:").map(|x| x.1.trim()) {
+                let plugin = Logs::plugin_name(first_line, &records);
+                add(LogProblem {
+                    kind: ProblemKind::MissingDependency,
+                    message: format!("{} is missing {}", plugin.as_deref().unwrap_or("A plugin"), dependencies),
+                    suggestion: format!("Install {} or remove the plugin", dependencies),
+                    plugin,
+                });
+            } else if let Some(others) = first_line.split_once("because it is incompatible with:").map(|x| x.1.trim()) {
+                let plugin = Logs::plugin_name(first_line, &records);
+                add(LogProblem {
+                    kind: ProblemKind::Incompatible,
+                    message: format!("{} does not work together with {}", plugin.as_deref().unwrap_or("A plugin"), others),
+                    suggestion: "Disable one of them".to_string(),
+                    plugin,
+                });
+            }
+
+            let found = [
+                ("TypeLoadException", ProblemKind::TypeLoad),
+                ("MissingMethodException", ProblemKind::MissingMember),
+                ("MissingFieldException", ProblemKind::MissingMember),
+            ]
+            .into_iter()
+            .find(|x| entry.message.contains(x.0));
+            let (exception, kind) = match found {
+                Some(found) => found,
+                None => continue,
+            };
+            // `Error loading [Name]`, an assembly of a plugin we installed, the plugin that logged it
+            // or the one that was being loaded, in that order
+            let plugin = Logs::plugin_name(first_line, &records)
+                .or_else(|| Logs::plugin_by_assembly(&entry.message, &records))
+                .or_else(|| Some(entry.source.clone()).filter(|x| !x.eq_ignore_ascii_case(self.loader.name()) && !x.is_empty()))
+                .or_else(|| loading.clone());
+            let line = entry.message.lines().find(|x| x.contains(exception)).unwrap_or(first_line).trim();
+            let line = line.strip_prefix("Error loading [").and_then(|x| x.split_once("]: ")).map(|x| x.1).unwrap_or(line);
+            add(LogProblem {
+                kind,
+                message: format!("{}: {}", plugin.as_deref().unwrap_or("Unknown plugin"), line),
+                suggestion: "The plugin was built for an older version of the game, update it or disable it until it is updated".to_string(),
+                plugin,
+            });
+        }
+
+        return problems;
+    }
+
+    /// Whether `GameAssembly.dll` is newer than the interop assemblies made from it.
+    pub fn interop_outdated(&self) -> bool {
+        let game_path = Path::new(&self.path);
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|x| x.modified()).ok();
+        let game_assembly = match modified(&game_path.join("GameAssembly.dll")) {
+            Some(modified) => modified,
+            None => return false,
+        };
+
+        let interop_path = game_path.join(self.loader.interop_path());
+        let newest = std::fs::read_dir(&interop_path)
+            .map(|x| x.filter_map(|x| x.ok()).filter_map(|x| modified(&x.path())).max())
+            .unwrap_or(None);
+
+        return match newest {
+            Some(newest) => newest < game_assembly,
+            None => false,
+        };
+    }
+
+    // the name inside `[Name 1.0.0]`, without the version
+    fn bracketed(text: &str) -> Option<String> {
+        let inner = text.split(']').next()?;
+        let name = match inner.rsplit_once(' ') {
+            Some((name, version)) if version.starts_with(|x: char| x.is_ascii_digit()) => name,
+            _ => inner,
+        };
+
+        return Some(name.trim().to_string()).filter(|x| !x.is_empty());
+    }
+
+    // `Could not load [Name 1.0.0]` and `Error loading [Name 1.0.0]` name the plugin
+    fn plugin_name(line: &str, records: &Records) -> Option<String> {
+        let name = line
+            .split_once("Could not load [")
+            .or_else(|| line.split_once("Error loading ["))
+            .and_then(|x| Logs::bracketed(x.1))?;
+
+        return Some(records.find(&name).map(|x| x.name.clone()).unwrap_or(name));
+    }
+
+    // `from assembly 'Name, Version=...'` belongs to the installed plugin that brought `Name.dll`
+    fn plugin_by_assembly(message: &str, records: &Records) -> Option<String> {
+        let assembly = message.split_once("assembly '")?.1.split([',', '\'']).next()?.trim().to_lowercase();
+        let file_name = format!("{}.dll", assembly);
+        let record = records.plugins.iter().find(|x| {
+            x.files.iter().any(|file| file.rsplit('/').next().map(|x| x.to_lowercase()) == Some(file_name.clone()))
+        })?;
+
+        return Some(record.name.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use crate::records::PluginRecord;
+
+    const LOG: &str = "\
+BepInEx 6.0.0-be.668 - Soulstone Survivors
+[Message:   BepInEx] Chainloader initialized
+[Info   :   BepInEx] Loading [CoolMod 1.2.0]
+[Error  :   BepInEx] Could not load [NeedsLib 1.0.0] because it has missing dependencies: SharedLib
+[Error  :   BepInEx] Could not load [Clash 2.0.0] because it is incompatible with: CoolMod
+[Error  : Il2CppInterop] System.TypeLoadException: Could not load type 'Foo' from assembly 'CoolMod, Version=1.2.0.0'
+[Error  : OtherMod] Exception in harmony patch
+System.MissingMethodException: Method not found: 'Void Player.Heal()'
+   at OtherMod.Patch.Postfix()
+";
+
+    #[test]
+    fn parse_joins_continuation_lines_to_their_entry() {
+        let entries = Logs::parse(LOG);
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0].level, LogLevel::Message);
+        assert_eq!(entries[0].source, "BepInEx");
+        assert_eq!(entries[0].message, "Chainloader initialized");
+        assert_eq!(entries[5].level, LogLevel::Error);
+        assert_eq!(entries[5].source, "OtherMod");
+        assert_eq!(entries[5].message.lines().count(), 3);
+
+        let entries = Logs::parse("[12:00:00.000] [CoolMod] Loaded\n[12:00:01.000] [ERROR] Failed to load Other.dll\n[12:00:02.000] [Clash] [WARNING] Old config\n");
+        assert_eq!(entries.len(), 3);
+        assert_eq!((entries[0].level, entries[0].source.as_str(), entries[0].message.as_str()), (LogLevel::Message, "CoolMod", "Loaded"));
+        assert_eq!((entries[1].level, entries[1].source.as_str(), entries[1].message.as_str()), (LogLevel::Error, "MelonLoader", "Failed to load Other.dll"));
+        assert_eq!((entries[2].level, entries[2].source.as_str(), entries[2].message.as_str()), (LogLevel::Warning, "Clash", "Old config"));
+    }
+
+    #[test]
+    fn analyze_finds_the_known_problems() {
+        let game = std::env::temp_dir().join(format!("bepinex_installer-logs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&game);
+        let path = game.to_string_lossy().to_string();
+        let records = Records {
+            plugins: vec![PluginRecord {
+                name: "CoolMod".to_string(),
+                version: "1.2.0".to_string(),
+                files: vec!["BepInEx/plugins/CoolMod/CoolMod.dll".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        records.save(&path).unwrap();
+
+        // the interop assemblies were generated before the game was updated
+        let logs = Logs::new(&path);
+        let interop_path = game.join(logs.loader.interop_path());
+        std::fs::create_dir_all(&interop_path).unwrap();
+        std::fs::write(interop_path.join("Assembly-CSharp.dll"), "interop").unwrap();
+        let game_assembly = std::fs::File::create(game.join("GameAssembly.dll")).unwrap();
+        game_assembly.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        drop(game_assembly);
+
+        let problems = logs.analyze(&Logs::parse(LOG));
+        let found = problems.iter().map(|x| (x.kind, x.plugin.as_deref())).collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (ProblemKind::OutdatedInterop, None),
+                (ProblemKind::MissingDependency, Some("NeedsLib")),
+                (ProblemKind::Incompatible, Some("Clash")),
+                (ProblemKind::TypeLoad, Some("CoolMod")),
+                (ProblemKind::MissingMember, Some("OtherMod")),
+            ]
+        );
+        assert_eq!(problems[1].message, "NeedsLib is missing SharedLib");
+        assert_eq!(problems[4].message, "OtherMod: System.MissingMethodException: Method not found: 'Void Player.Heal()'");
+
+        std::fs::remove_file(game.join("GameAssembly.dll")).unwrap();
+        assert!(!logs.interop_outdated());
+        std::fs::remove_dir_all(&game).unwrap();
+    }
+}
@@ -1,90 +1,1166 @@
-mod steam;
-mod installer;
-mod config;
-mod plugins;
-mod github_repositories;
-mod plugin;
-mod github_releases;
-
-use std::io::Read;
-use ansi_term::Color::{Red, White, Green, Yellow};
-use ansi_term::{Colour, enable_ansi_support};
-use crate::config::*;
-use crate::installer::Installer;
-use crate::steam::Steam;
+// every function ends in an explicit `return`
+#![allow(clippy::needless_return)]
+
+mod console;
+#[cfg(feature = "gui")]
+mod gui;
+mod progress;
+mod report;
+mod tui;
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use ansi_term::Color::{Red, White, Yellow};
+use ansi_term::{Colour, Style};
+#[cfg(target_os = "windows")]
+use ansi_term::enable_ansi_support;
+use bepinex_installer::config::*;
+use bepinex_installer::{Acceptable, Backup, CfgEntry, CfgFile, CheckStatus, Doctor, FileProblem, GameUpdates, Installer, InstallerError, LaunchMode, LoaderState, LogEntry, LogLevel, Logs, Observer, Profiles, Records, Settings, Steam, SteamGame};
+use crate::console::ConsoleObserver;
+use crate::report::{JsonObserver, LoaderReport, Output};
 
 
 fn main() {
-    // enable ansi support
-    let _ = enable_ansi_support();
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    let settings = load_settings(&mut args);
 
-    // set title of console via ansi
-    print!("\x1b]0;{} - v{}\x07", TITLE, VERSION);
-    // hide cursor
-    print!("\x1b[?25l");
+    // gui builds open the window unless a console command is given
+    #[cfg(feature = "gui")]
+    if args.is_empty() || args[0] == "gui" {
+        match settings {
+            Ok(settings) => {
+                settings.apply();
+                if let Err(err) = gui::run() {
+                    eprintln!("Could not open the installer window: {}", err);
+                }
+            }
+            Err(err) => eprintln!("{}", err),
+        }
+        return;
+    }
 
-    println!("{} v{}", TITLE, VERSION);
-    println!("Author: {}", AUTHOR);
-    println!("License: {}", LICENSE);
-    println!("{}", Red.paint("Note: If you get rate-limited, please wait a few minutes and try again."));
-    println!("{}", Red.paint("Unfortunately, this is a limitation of GitHub's API."));
-    println!();
+    let json = take_flag(&mut args, "--json");
+    let mut output = Output::new(json);
 
-    begin_installation();
+    if !json {
+        // enable ansi support, other terminals have it on already
+        #[cfg(target_os = "windows")]
+        let _ = enable_ansi_support();
+
+        // set title of console via ansi
+        print!("\x1b]0;{} - v{}\x07", TITLE, VERSION);
+        // hide cursor
+        print!("\x1b[?25l");
+
+        println!("{} v{}", TITLE, VERSION);
+        println!("Author: {}", AUTHOR);
+        println!("License: {}", LICENSE);
+        println!("{}", Red.paint("Note: If you get rate-limited, please wait a few minutes and try again."));
+        println!("{}", Red.paint("Unfortunately, this is a limitation of GitHub's API."));
+        println!();
+    }
+
+    let jobs = match take_option(&mut args, "--jobs").map(|x| x.parse::<usize>()) {
+        None => DOWNLOAD_JOBS,
+        Some(Ok(jobs)) if jobs > 0 => jobs,
+        Some(_) => {
+            output.warning("--jobs expects a number greater than 0");
+            DOWNLOAD_JOBS
+        }
+    };
+
+    let tui = !take_flag(&mut args, "--no-tui");
+
+    // a broken config stops every command, it might point somewhere unexpected
+    let settings = match settings {
+        Ok(settings) => Some(settings),
+        Err(err) => {
+            output.failure(&err);
+            None
+        }
+    };
+    if let Some(settings) = &settings {
+        settings.clone().apply();
+    }
+
+    match args.first().map(|x| x.as_str()) {
+        _ if settings.is_none() => {}
+        None => begin_installation(&mut output, jobs, tui, &[]),
+        Some("install") => begin_installation(&mut output, jobs, tui, &args[1..]),
+        Some("backup") => begin_backup(&mut output, &args[1..]),
+        Some("restore") => begin_restore(&mut output, &args[1..]),
+        Some("uninstall") => begin_uninstall(&mut output, &args[1..]),
+        Some("status") => begin_status(&mut output),
+        Some("loader") => begin_loader(&mut output, &args[1..]),
+        Some("first-run") => begin_first_run(&mut output, &args[1..]),
+        Some("launch") => begin_launch(&mut output, &args[1..]),
+        Some("logs") => begin_logs(&mut output, &args[1..]),
+        Some("doctor") => begin_doctor(&mut output, &args[1..]),
+        Some("verify") => begin_verify(&mut output),
+        Some("repair") => begin_repair(&mut output),
+        Some("conflicts") => begin_conflicts(&mut output, &args[1..]),
+        Some("enable") => begin_toggle(&mut output, &args[1..], true),
+        Some("disable") => begin_toggle(&mut output, &args[1..], false),
+        Some("profile") => begin_profile(&mut output, &args[1..]),
+        Some("config") => begin_config(&mut output, &args[1..]),
+        Some("settings") => begin_settings(&mut output, &args[1..]),
+        Some(command) => {
+            output.error(&format!("Unknown command: {}", command));
+            if !json {
+                print_usage();
+            }
+        }
+    }
+
+    // scripts get the report instead of a prompt
+    if json {
+        output.finish();
+        return;
+    }
 
     // press any key to exit
     println!("{}", White.paint("Press any key to exit..."));
-    std::io::stdin().read(&mut [0]).unwrap();
+    let _ = std::io::stdin().read(&mut [0]);
 }
 
-fn begin_installation() {
-    let gray = Colour::RGB(128, 128, 128);
+// config file first, then environment variables, then flags
+fn load_settings(args: &mut Vec<String>) -> Result<Settings, InstallerError> {
+    // take the flags even if the file is broken so they are not read as commands
+    let game_path = take_option(args, "--game-path");
+    let token = take_option(args, "--token");
+    let channel = take_option(args, "--channel");
+    let proxy = take_option(args, "--proxy");
+    // `--loader` alone already means the loader to `uninstall`
+    let loader = take_option(args, "--mod-loader");
+    let mut cfg = Vec::new();
+    while let Some(value) = take_option(args, "--cfg") {
+        cfg.push(value);
+    }
+
+    let mut settings = Settings::load()?;
+    settings.apply_env()?;
+    if game_path.is_some() {
+        settings.game_path = game_path;
+    }
+    if token.is_some() {
+        settings.github_token = token;
+    }
+    if let Some(channel) = channel {
+        settings.channel = channel.parse()?;
+    }
+    if proxy.is_some() {
+        settings.proxy = proxy;
+    }
+    if let Some(loader) = loader {
+        settings.loader = Some(loader.parse()?);
+    }
+    for value in cfg {
+        settings.add_bepinex_cfg(&value)?;
+    }
 
-    println!("{}", gray.paint("Checking for Steam..."));
+    return Ok(settings);
+}
+
+// removes `--name value` from the arguments and returns the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index = args.iter().position(|x| x == name)?;
+    args.remove(index);
+    if index < args.len() {
+        return Some(args.remove(index));
+    }
+
+    return None;
+}
+
+// removes `name` from the arguments and returns whether it was there
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let length = args.len();
+    args.retain(|x| x != name);
+    return args.len() != length;
+}
+
+fn print_usage() {
+    println!("Usage: bepinex_installer [command] [options]");
+    #[cfg(feature = "gui")]
+    println!("  gui                    Open the installer window (default)");
+    println!("  install [plugin...]    Install the mod loader and plugins (default)");
+    println!("    --jobs <n>           Download up to n plugins at the same time");
+    println!("    --no-tui             Ask about every plugin instead of opening the plugin browser");
+    println!("    --cfg <section.key=value>");
+    println!("                         Set a BepInEx.cfg value once the file exists, can be given more than once");
+    println!("  backup                 Snapshot the plugins, config and loader files");
+    println!("  backup list            List all snapshots");
+    println!("  restore <snapshot>     Restore a snapshot");
+    println!("  uninstall <plugin>     Remove a plugin");
+    println!("  uninstall --loader     Remove the mod loader and all plugins");
+    println!("  status                 Show the loader, the profile and the installed plugins");
+    println!("  loader enable          Load the mod loader when the game starts");
+    println!("  loader disable         Start the game unmodded, the mod loader stays installed");
+    println!("  first-run              Start the game once so the mod loader can set itself up, then close it");
+    println!("    --timeout <seconds>  Give up after this long (default {})", FIRST_RUN_TIMEOUT);
+    println!("  launch                 Start the game through Steam");
+    println!("    --vanilla            Start it unmodded, the mod loader is enabled again once the game exits");
+    println!("    --profile <name>     Start it with a profile, the previous plugins come back once the game exits");
+    println!("    --direct             Start the executable in the game folder instead of going through Steam (Windows only)");
+    println!("  logs                   Show the end of the BepInEx log and the problems found in it");
+    println!("    --lines <n>          Show the last n entries (default {})", LOG_LINES);
+    println!("    --level <level>      Only show entries at this level or worse");
+    println!("    --source <name>      Only show entries logged by this plugin");
+    println!("    --follow             Keep showing new entries while the game runs");
+    println!("  doctor                 Check the setup and write a zip with the results to send along with a bug report");
+    println!("    --output <folder>    Write the zip into this folder instead of the current one");
+    println!("  verify                 Check the files of the mod loader and the plugins against what was installed");
+    println!("  repair                 Download and put back the files verify finds missing or changed");
+    println!("  conflicts              List the files of other mod loaders that would start together with this one");
+    println!("  conflicts remove       Snapshot the mod setup and remove the other mod loaders");
+    println!("  enable <plugin>        Load a disabled plugin again");
+    println!("  disable <plugin>       Keep a plugin installed but stop the mod loader from loading it");
+    println!("  profile list           List all profiles");
+    println!("  profile save <name>    Save the enabled plugins and config files as a profile");
+    println!("  profile switch <name>  Enable the plugins and config files of a profile");
+    println!("  profile delete <name>  Delete a profile");
+    println!("  config list [file]     List the BepInEx config files or the settings in one");
+    println!("  config get <file> <section> <key>");
+    println!("                         Show a setting with its description and default");
+    println!("  config set <file> <section> <key> <value>");
+    println!("                         Change a setting");
+    println!("  config reset <file> [<section> <key>]");
+    println!("                         Put a setting, or every setting in the file, back to its default");
+    println!("  settings               Show the installer settings and where they are stored");
+    println!("  settings init          Write the installer settings to the settings file");
+    println!("Options:");
+    println!("  --game-path <path>     Use this game folder instead of looking through Steam");
+    println!("  --token <token>        GitHub token, raises the rate limit");
+    println!("  --channel <channel>    Use stable or prerelease plugin releases");
+    println!("  --proxy <url>          Send every request through this proxy");
+    println!("  --mod-loader <loader>  Use bepinex or melonloader (default bepinex)");
+    println!("  --json                 Print the results as json, plugins are only installed when named");
+}
+
+fn find_game(output: &mut Output) -> Option<SteamGame> {
+    // a configured game folder skips steam
+    if let Some(path) = &Settings::current().game_path {
+        return match Steam::game_at(path) {
+            Ok(game) => {
+                output.success(&format!("Using game folder {}", game.path));
+                output.report.game = Some(game.clone());
+                Some(game)
+            }
+            Err(err) => {
+                output.failure(&err);
+                None
+            }
+        };
+    }
+
+    output.info("Checking for Steam...");
     let steam_path = Steam::get_steam_path();
     if steam_path.is_none() {
-        println!("{}", Red.paint("Steam not found!"));
-        return;
+        output.error("Steam not found!");
+        return None;
     }
-    println!("{}", Green.paint("Steam found!"));
+    output.success("Steam found!");
     let steam_path = steam_path.unwrap();
-    println!("{}", gray.paint("Checking for games..."));
+    output.report.steam_path = Some(steam_path.clone());
+    output.info("Checking for games...");
     let games = Steam::iterate_games(&steam_path);
     if games.is_none() {
-        println!("{}", Red.paint("No games found!"));
-        return;
+        output.error("No games found!");
+        return None;
     }
-    println!("{}", Green.paint(format!("{} Games found!", games.as_ref().unwrap().len())));
+    output.success(&format!("{} Games found!", games.as_ref().unwrap().len()));
     let games = games.unwrap();
-    let soulstone = games.iter().find(|game| game.id == "2066020");
+    let soulstone = games.iter().find(|game| game.id == GAME_ID).cloned();
+    output.report.games = games;
     if soulstone.is_none() {
-        println!("{}", Red.paint("Soulstone Survivors not found!"));
+        output.error("Soulstone Survivors not found!");
+        return None;
+    }
+    output.success("Soulstone Survivors found!");
+    output.report.game = soulstone.clone();
+
+    return soulstone;
+}
+
+fn begin_settings(output: &mut Output, args: &[String]) {
+    let path = Settings::path().map(|x| x.display().to_string()).unwrap_or("none".to_string());
+    let mut settings = Settings::current().clone();
+
+    if args.first().map(|x| x.as_str()) == Some("init") {
+        match settings.save() {
+            Ok(_) => output.success(&format!("Settings written to {}", path)),
+            Err(err) => {
+                output.failure(&err);
+                return;
+            }
+        }
+    }
+
+    // never show the token itself
+    if settings.github_token.is_some() {
+        settings.github_token = Some("********".to_string());
+    }
+    output.plain(&format!("Settings file: {}", path));
+    output.plain(&format!("Game path: {}", settings.game_path.as_deref().unwrap_or("found through Steam")));
+    output.plain(&format!("GitHub token: {}", settings.github_token.as_deref().unwrap_or("none")));
+    output.plain(&format!("Sources: {}", settings.sources.join(", ")));
+    output.plain(&format!("Channel: {:?}", settings.channel));
+    output.plain(&format!("Mod loader: {}", settings.loader.map(|x| x.loader().name()).unwrap_or("BepInEx (default)")));
+    output.plain(&format!("Cache TTL: {} seconds", settings.cache_ttl));
+    output.plain(&format!("Proxy: {}", settings.proxy.as_deref().unwrap_or("none")));
+    output.plain(&format!("Default plugins: {}", settings.default_plugins.join(", ")));
+    output.report.settings = Some(settings);
+}
+
+fn begin_backup(output: &mut Output, args: &[String]) {
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let backup = Backup::new(&soulstone_game.path);
+
+    if args.first().map(|x| x.as_str()) == Some("list") {
+        print_snapshots(output, &backup);
+        return;
+    }
+
+    output.info("Creating snapshot...");
+    match backup.create() {
+        Ok(snapshot) => {
+            output.success(&format!("Snapshot {} created! ({} KB)", snapshot.name, snapshot.size / 1024));
+            output.report.snapshots.push(snapshot);
+        }
+        Err(err) => output.failure(&err),
+    }
+}
+
+fn begin_restore(output: &mut Output, args: &[String]) {
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let backup = Backup::new(&soulstone_game.path);
+
+    let name = match args.first() {
+        Some(name) => name,
+        None => {
+            output.error("No snapshot given, pick one of the following:");
+            print_snapshots(output, &backup);
+            return;
+        }
+    };
+
+    output.info(&format!("Restoring {}...", name));
+    match backup.restore(name) {
+        Ok(result) => {
+            output.success(&format!("Snapshot restored! {} files restored, {} files removed.", result.restored, result.removed));
+            output.report.restored = Some(result);
+        }
+        Err(err) => output.failure(&err),
+    }
+}
+
+fn begin_uninstall(output: &mut Output, args: &[String]) {
+    let name = match args.first() {
+        Some(name) => name,
+        None => {
+            output.error("No plugin given");
+            if !output.is_json() {
+                print_usage();
+            }
+            return;
+        }
+    };
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let installer = Installer::new(&soulstone_game.path);
+
+    if name == "--loader" {
+        let name = installer.loader().name();
+        output.info(&format!("Removing {}...", name));
+        match installer.uninstall() {
+            Ok(_) => {
+                output.success(&format!("{} removed!", name));
+                output.report.loader = Some(loader_report(&installer, true));
+            }
+            Err(err) => output.failure(&err),
+        }
+        return;
+    }
+
+    match installer.uninstall_plugin(name) {
+        Ok(record) => {
+            output.success(&format!("{} {} removed!", record.name, record.version));
+            output.report.removed = Some(record);
+        }
+        Err(err) => output.failure(&err),
+    }
+}
+
+fn loader_report(installer: &Installer, changed: bool) -> LoaderReport {
+    return LoaderReport {
+        kind: installer.loader().kind(),
+        installed: installer.is_installed(),
+        state: installer.loader_state(),
+        version: installer.loader_version(),
+        ready: installer.has_ran(),
+        changed,
+    };
+}
+
+fn begin_status(output: &mut Output) {
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    check_game_update(output, &soulstone_game, false);
+    let installer = Installer::new(&soulstone_game.path);
+    let records = Records::load(&soulstone_game.path);
+
+    let loader = loader_report(&installer, false);
+    let version = loader.version.clone().unwrap_or("unknown version".to_string());
+    let name = installer.loader().name();
+    match loader.state {
+        LoaderState::Missing => output.warning(&format!("{}: not installed", name)),
+        LoaderState::Enabled if !loader.ready => {
+            output.warning(&format!("{}: installed ({}), but the first launch has not finished yet, run first-run or start the game once", name, version));
+        }
+        LoaderState::Enabled => output.success(&format!("{}: enabled ({})", name, version)),
+        LoaderState::Disabled => output.warning(&format!("{}: disabled ({}), the game starts unmodded", name, version)),
+    }
+    output.report.loader = Some(loader);
+
+    if let Some(profile) = &records.profile {
+        output.plain(&format!("Profile: {}", profile));
+    }
+    output.report.active_profile = records.profile.clone();
+
+    if records.plugins.is_empty() {
+        output.plain("No plugins installed by the installer");
+    }
+    for record in &records.plugins {
+        let state = if record.disabled { "disabled" } else { "enabled" };
+        output.plain(&format!("  {} {} ({})", record.name, record.version, state));
+    }
+    output.report.installed = records.plugins;
+}
+
+fn begin_first_run(output: &mut Output, args: &[String]) {
+    let timeout = match args {
+        [] => FIRST_RUN_TIMEOUT,
+        [flag, seconds] if flag == "--timeout" && seconds.parse::<u64>().is_ok() => seconds.parse().unwrap(),
+        _ => {
+            output.error("Usage: first-run [--timeout <seconds>]");
+            return;
+        }
+    };
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let mut installer = Installer::new(&soulstone_game.path);
+    if !output.is_json() {
+        installer.set_observer(Arc::new(ConsoleObserver::new(false)));
+    }
+
+    if installer.has_ran() {
+        output.success(&format!("{} already finished its first launch!", installer.loader().name()));
+    } else {
+        output.info("Starting Soulstone Survivors through Steam...");
+        if let Err(err) = installer.first_run(Duration::from_secs(timeout)) {
+            output.failure(&err);
+            output.report.loader = Some(loader_report(&installer, false));
+            return;
+        }
+        output.success(&format!("{} finished its first launch, plugins are loaded from now on!", installer.loader().name()));
+    }
+    output.report.loader = Some(loader_report(&installer, false));
+
+    // BepInEx.cfg exists now
+    match installer.apply_presets(&Settings::current().bepinex_cfg) {
+        Ok(presets) => {
+            if !presets.applied.is_empty() {
+                output.success(&format!("BepInEx.cfg updated: {}", presets.applied.join(", ")));
+            }
+            output.report.presets = Some(presets);
+        }
+        Err(err) => output.failure(&err),
+    }
+}
+
+fn begin_launch(output: &mut Output, args: &[String]) {
+    let mut args = args.to_vec();
+    let direct = take_flag(&mut args, "--direct");
+    let vanilla = take_flag(&mut args, "--vanilla");
+    let modded = take_flag(&mut args, "--modded");
+    let profile = take_option(&mut args, "--profile");
+    let mode = match (vanilla, modded, profile) {
+        (false, _, None) => LaunchMode::Modded,
+        (true, false, None) => LaunchMode::Vanilla,
+        (false, false, Some(profile)) => LaunchMode::Profile(profile),
+        _ => {
+            output.error("Usage: launch [--modded | --vanilla | --profile <name>] [--direct]");
+            return;
+        }
+    };
+    if !args.is_empty() {
+        output.error("Usage: launch [--modded | --vanilla | --profile <name>] [--direct]");
+        return;
+    }
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    check_game_update(output, &soulstone_game, false);
+    let mut installer = Installer::new(&soulstone_game.path);
+    if !output.is_json() {
+        installer.set_observer(Arc::new(ConsoleObserver::new(false)));
+    }
+
+    match &mode {
+        LaunchMode::Modded => output.info(&format!("Starting Soulstone Survivors with {}...", installer.loader().name())),
+        LaunchMode::Vanilla => output.info("Starting Soulstone Survivors without mods..."),
+        LaunchMode::Profile(name) => output.info(&format!("Starting Soulstone Survivors with profile {}...", name)),
+    }
+    match installer.launch(&mode, direct) {
+        Ok(restore) if restore.is_empty() => output.success("Soulstone Survivors started!"),
+        Ok(restore) => {
+            output.success("The game exited, the previous setup is back!");
+            output.report.launch_restored = Some(restore);
+        }
+        Err(err) => output.failure(&err),
+    }
+    output.report.launched = Some(mode);
+}
+
+fn begin_logs(output: &mut Output, args: &[String]) {
+    let mut args = args.to_vec();
+    let source = take_option(&mut args, "--source").map(|x| x.to_lowercase());
+    let level = take_option(&mut args, "--level");
+    let lines = take_option(&mut args, "--lines");
+    let follow = take_flag(&mut args, "--follow");
+    if !args.is_empty() {
+        output.error("Usage: logs [--lines <n>] [--level <level>] [--source <plugin>] [--follow]");
+        return;
+    }
+    let level = match level.map(|x| x.parse::<LogLevel>()).transpose() {
+        Ok(level) => level.unwrap_or(LogLevel::Debug),
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+    let lines = match lines.map(|x| x.parse::<usize>()) {
+        None => LOG_LINES,
+        Some(Ok(lines)) => lines,
+        Some(Err(_)) => {
+            output.error("--lines expects a number");
+            return;
+        }
+    };
+    if follow && output.is_json() {
+        output.error("--follow can not be used with --json");
+        return;
+    }
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let logs = Logs::new(&soulstone_game.path);
+    let entries = match logs.read() {
+        Ok(entries) => entries,
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+
+    let filter = |entry: &LogEntry| entry.level <= level && source.as_ref().is_none_or(|x| entry.source.to_lowercase().contains(x));
+    let shown = entries.iter().filter(|x| filter(x)).cloned().collect::<Vec<_>>();
+    let shown = shown[shown.len().saturating_sub(lines)..].to_vec();
+    for entry in &shown {
+        let text = format!("[{:<7}:{:>10}] {}", format!("{:?}", entry.level), entry.source, entry.message);
+        output.plain(&log_style(entry.level).paint(text).to_string());
+    }
+    output.report.log_entries = shown;
+
+    // problems are looked for in the whole log, not only in what is shown
+    let problems = logs.analyze(&entries);
+    if problems.is_empty() {
+        output.success("No known problems found in the log!");
+    }
+    for problem in &problems {
+        output.plain(&Red.paint(&problem.message).to_string());
+        output.plain(&Colour::RGB(128, 128, 128).paint(format!("\u{2514}\u{2500}\u{2500}\u{2500} {}", problem.suggestion)).to_string());
+    }
+    output.report.log_problems = problems;
+
+    if follow {
+        output.info("Waiting for new log entries, close the installer to stop...");
+        follow_log(output, &logs, filter);
+    }
+}
+
+// prints what is appended to the log until the installer is closed, a new launch starts from the top
+fn follow_log(output: &Output, logs: &Logs, filter: impl Fn(&LogEntry) -> bool) {
+    let log_path = logs.log_path();
+    let length = || std::fs::metadata(&log_path).map(|x| x.len()).unwrap_or(0);
+    let mut offset = length();
+    let mut pending = Vec::new();
+    // continuation lines belong to the entry before them
+    let mut last: Option<LogEntry> = None;
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let current = length();
+        if current < offset {
+            output.info("The log was started over");
+            offset = 0;
+            pending.clear();
+        }
+        if current == offset {
+            continue;
+        }
+
+        let read = std::fs::File::open(&log_path).and_then(|mut file| {
+            file.seek(SeekFrom::Start(offset))?;
+            return file.read_to_end(&mut pending);
+        });
+        match read {
+            Ok(read) => offset += read as u64,
+            Err(_) => continue,
+        }
+
+        // only complete lines, the rest waits for the next read
+        while let Some(index) = pending.iter().position(|x| *x == b'\n') {
+            let line = String::from_utf8_lossy(&pending[..index]).trim_end_matches('\r').to_string();
+            pending.drain(..=index);
+            if let Some(entry) = LogEntry::parse_line(&line) {
+                last = Some(entry);
+            }
+            if let Some(entry) = last.as_ref().filter(|x| filter(x)) {
+                output.plain(&log_style(entry.level).paint(line).to_string());
+            }
+        }
+    }
+}
+
+fn log_style(level: LogLevel) -> Style {
+    return match level {
+        LogLevel::Fatal | LogLevel::Error => Red.normal(),
+        LogLevel::Warning => Yellow.normal(),
+        LogLevel::Message => White.bold(),
+        LogLevel::Info => Style::new(),
+        LogLevel::Debug => Colour::RGB(128, 128, 128).normal(),
+    };
+}
+
+fn begin_doctor(output: &mut Output, args: &[String]) {
+    let mut args = args.to_vec();
+    let folder = take_option(&mut args, "--output").unwrap_or(".".to_string());
+    if !args.is_empty() {
+        output.error("Usage: doctor [--output <folder>]");
+        return;
+    }
+
+    output.info("Checking the setup...");
+    let diagnosis = Doctor::diagnose();
+    let gray = Colour::RGB(128, 128, 128);
+    for check in &diagnosis.checks {
+        let line = format!("{}: {}", check.name, check.detail);
+        match check.status {
+            CheckStatus::Ok => output.success(&line),
+            CheckStatus::Warning => output.plain(&Yellow.paint(line).to_string()),
+            CheckStatus::Failed => output.plain(&Red.paint(line).to_string()),
+            CheckStatus::Skipped => output.info(&line),
+        }
+        if let Some(suggestion) = &check.suggestion {
+            output.plain(&gray.paint(format!("\u{2514}\u{2500}\u{2500}\u{2500} {}", suggestion)).to_string());
+        }
+    }
+
+    let problems = diagnosis.problems();
+    match Doctor::write_bundle(&diagnosis, Path::new(&folder)) {
+        Ok(path) => {
+            output.success(&format!("{} problems found, attach {} to your bug report", problems, path.display()));
+            output.report.bundle = Some(path);
+        }
+        Err(err) => output.failure(&err),
+    }
+    output.report.diagnosis = Some(diagnosis);
+}
+
+// steam updates the game on its own, the interop assemblies and plugins are made for one build.
+// only `install` asks what to do about it, the other commands just report it
+fn check_game_update(output: &mut Output, game: &SteamGame, interactive: bool) {
+    let updates = GameUpdates::new(&game.path);
+    let update = match updates.check() {
+        Ok(Some(update)) => update,
+        Ok(None) => return,
+        Err(err) => {
+            output.warning(&format!("Could not check for game updates: {}", err));
+            return;
+        }
+    };
+
+    output.warning(&format!("Soulstone Survivors was updated from build {} to {}", update.previous_build, update.build));
+    if !update.affected.is_empty() {
+        output.warning(&format!("These plugins might not work with the new build: {}", update.affected.join(", ")));
+        output.info("Checking for plugin updates...");
+        match updates.plugin_updates(&update) {
+            Ok(plugin_updates) => {
+                for plugin_update in &plugin_updates {
+                    let released = if plugin_update.compatible { "released after the game update" } else { "released before the game update" };
+                    output.plain(&format!("{} {} -> {} ({})", plugin_update.name, plugin_update.installed, plugin_update.latest, released));
+                }
+                if !plugin_updates.is_empty() {
+                    let gray = Colour::RGB(128, 128, 128);
+                    output.plain(&gray.paint("\u{2514}\u{2500}\u{2500}\u{2500} Run install and pick the plugins to update them").to_string());
+                }
+                output.report.plugin_updates = plugin_updates;
+            }
+            Err(err) => output.warning(&format!("Could not check for plugin updates: {}", err)),
+        }
+    }
+
+    // the new build is recorded once the interop is cleared or `install` set up the plugins for it
+    let loader = Installer::new(&game.path).loader();
+    if interactive && !output.is_json() && update.interop && ConsoleObserver::new(false).confirm(&format!("Clear {} so {} generates it again for the new build?", loader.interop_path(), loader.name())) {
+        match updates.clear_interop().and_then(|_| updates.acknowledge()) {
+            Ok(_) => output.success(&format!("{} cleared, it is generated again on the next launch!", loader.interop_path())),
+            Err(err) => output.failure(&err),
+        }
+    } else if !interactive {
+        output.info("Run install to set up the plugins for the new build");
+    }
+    output.report.game_update = Some(update);
+}
+
+fn begin_conflicts(output: &mut Output, args: &[String]) {
+    let remove = match args.first().map(|x| x.as_str()) {
+        None => false,
+        Some("remove") => true,
+        _ => {
+            output.error("Usage: conflicts [remove]");
+            return;
+        }
+    };
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let installer = Installer::new(&soulstone_game.path);
+    let name = installer.loader().name();
+
+    let conflicts = installer.conflicts();
+    for conflict in &conflicts {
+        output.plain(&format!("{} belongs to {}", conflict.path, conflict.owner()));
+    }
+    if conflicts.is_empty() {
+        output.success(&format!("No other mod loader found, only {} starts with the game!", name));
+    } else if !remove {
+        output.warning(&format!("Another mod loader would start together with {}, run conflicts remove to back it up and remove it", name));
+    }
+    output.report.conflicts = conflicts;
+    if !remove || output.report.conflicts.is_empty() {
         return;
     }
-    println!("{}", Green.paint("Soulstone Survivors found!"));
-    let soulstone_game = soulstone.unwrap();
+
+    output.info("Creating snapshot and removing the other mod loaders...");
+    match installer.remove_conflicts() {
+        Ok(snapshot) => {
+            output.success(&format!("Removed, snapshot {} has a copy of everything!", snapshot.name));
+            output.report.snapshots.push(snapshot);
+        }
+        Err(err) => output.failure(&err),
+    }
+}
+
+fn begin_verify(output: &mut Output) {
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let installer = Installer::new(&soulstone_game.path);
+
+    output.info("Checking the installed files...");
+    let verified = match installer.verify() {
+        Ok(verified) => verified,
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+    for issue in &verified.issues {
+        let problem = match issue.problem {
+            FileProblem::Missing => "is missing",
+            FileProblem::Changed => "was changed",
+        };
+        output.plain(&Red.paint(format!("{} {} ({})", issue.path, problem, issue.owner)).to_string());
+    }
+    for name in &verified.unverified {
+        output.warning(&format!("{} was installed without hashes, only missing files were found", name));
+    }
+    if verified.issues.is_empty() {
+        output.success(&format!("All {} files are intact!", verified.checked));
+    } else {
+        output.warning(&format!("{} of {} files are missing or changed, run repair to restore them", verified.issues.len(), verified.checked));
+    }
+    output.report.verified = Some(verified);
+}
+
+fn begin_repair(output: &mut Output) {
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let mut installer = Installer::new(&soulstone_game.path);
+    if !output.is_json() {
+        installer.set_observer(Arc::new(ConsoleObserver::new(false)));
+    }
+
+    output.info("Checking the installed files...");
+    let repaired = match installer.repair() {
+        Ok(repaired) => repaired,
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+    for issue in &repaired.restored {
+        output.plain(&format!("Restored {} ({})", issue.path, issue.owner));
+    }
+    for (owner, err) in &repaired.failed {
+        output.error(&format!("Could not repair {}: {}", owner, err));
+    }
+    if repaired.failed.is_empty() {
+        output.success(&format!("{} files restored, everything is intact!", repaired.restored.len()));
+    } else {
+        output.warning(&format!("{} files restored, {} could not be repaired", repaired.restored.len(), repaired.failed.len()));
+    }
+    output.report.repaired = Some(repaired);
+}
+
+fn begin_loader(output: &mut Output, args: &[String]) {
+    let enabled = match args.first().map(|x| x.as_str()) {
+        Some("enable") => true,
+        Some("disable") => false,
+        _ => {
+            output.error("Usage: loader enable | loader disable");
+            return;
+        }
+    };
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
     let installer = Installer::new(&soulstone_game.path);
 
-    println!("{}", gray.paint("Installing BepInEx..."));
+    let changed = installer.loader_state() != if enabled { LoaderState::Enabled } else { LoaderState::Disabled };
+    match installer.set_loader_enabled(enabled) {
+        Ok(_) if enabled => output.success(&format!("{} enabled, the game starts modded!", installer.loader().name())),
+        Ok(_) => output.success(&format!("{} disabled, the game starts unmodded!", installer.loader().name())),
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    }
+    output.report.loader = Some(loader_report(&installer, changed));
+}
+
+fn begin_toggle(output: &mut Output, args: &[String], enabled: bool) {
+    let name = match args.first() {
+        Some(name) => name,
+        None => {
+            output.error("No plugin given");
+            if !output.is_json() {
+                print_usage();
+            }
+            return;
+        }
+    };
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let installer = Installer::new(&soulstone_game.path);
+
+    match installer.set_plugin_enabled(name, enabled) {
+        Ok(record) => {
+            let state = if enabled { "enabled" } else { "disabled" };
+            output.success(&format!("{} {} {}!", record.name, record.version, state));
+            output.report.toggled = Some(record);
+        }
+        Err(err) => output.failure(&err),
+    }
+}
+
+fn begin_profile(output: &mut Output, args: &[String]) {
+    let command = args.first().map(|x| x.as_str());
+    let name = args.get(1);
+    if command.is_none() || (command != Some("list") && name.is_none()) {
+        output.error("Usage: profile list | profile save <name> | profile switch <name> | profile delete <name>");
+        return;
+    }
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    let profiles = Profiles::new(&soulstone_game.path);
+
+    match (command.unwrap(), name) {
+        ("save", Some(name)) => match profiles.save(name) {
+            Ok(profile) => {
+                output.success(&format!("Profile {} saved with {} plugins!", profile.name, profile.plugins.len()));
+                output.report.profiles.push(profile);
+            }
+            Err(err) => output.failure(&err),
+        },
+        ("switch", Some(name)) => {
+            output.info(&format!("Switching to {}...", name));
+            match profiles.switch(name) {
+                Ok(result) => {
+                    for plugin in &result.enabled {
+                        output.plain(&format!("+ {}", plugin));
+                    }
+                    for plugin in &result.disabled {
+                        output.plain(&format!("- {}", plugin));
+                    }
+                    output.success(&format!("Switched to {}! {} plugins enabled, {} disabled, {} config files.", name, result.enabled.len(), result.disabled.len(), result.configs));
+                    output.report.switched = Some(result);
+                }
+                Err(err) => output.failure(&err),
+            }
+        }
+        ("delete", Some(name)) => match profiles.delete(name) {
+            Ok(_) => output.success(&format!("Profile {} deleted!", name)),
+            Err(err) => output.failure(&err),
+        },
+        ("list", _) => match profiles.list() {
+            Ok(list) => {
+                let active = profiles.active();
+                if list.is_empty() {
+                    output.warning("No profiles found!");
+                }
+                for profile in &list {
+                    let marker = if active.as_deref() == Some(profile.name.as_str()) { "*" } else { " " };
+                    output.plain(&format!("{} {} ({})", marker, profile.name, profile.plugins.join(", ")));
+                }
+                output.report.profiles = list;
+                output.report.active_profile = active;
+            }
+            Err(err) => output.failure(&err),
+        },
+        (command, _) => output.error(&format!("Unknown profile command: {}", command)),
+    }
+}
+
+fn begin_config(output: &mut Output, args: &[String]) {
+    let command = args.first().map(|x| x.as_str());
+    let usage = "Usage: config list [file] | config get <file> <section> <key> | config set <file> <section> <key> <value> | config reset <file> [<section> <key>]";
+    let valid = match command {
+        Some("list") => true,
+        Some("get") => args.len() == 4,
+        Some("set") => args.len() >= 5,
+        Some("reset") => args.len() == 2 || args.len() == 4,
+        _ => false,
+    };
+    if !valid {
+        output.error(usage);
+        return;
+    }
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+
+    let loader = Installer::new(&soulstone_game.path).loader();
+    let config_path = Path::new(&soulstone_game.path).join(loader.config_path());
+
+    // without a file name list the files themselves
+    let name = match args.get(1) {
+        Some(name) => name,
+        None => {
+            match CfgFile::list(&config_path) {
+                Ok(files) => {
+                    if files.is_empty() {
+                        output.warning(&format!("No config files found, start the game once with {} to create them", loader.name()));
+                    }
+                    for file in &files {
+                        output.plain(file);
+                    }
+                    output.report.config_files = files;
+                }
+                Err(err) => output.failure(&err),
+            }
+            return;
+        }
+    };
+    let path = match CfgFile::find(&config_path, name) {
+        Ok(path) => path,
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+    let mut file = match CfgFile::load(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+
+    let changed = match (command.unwrap(), args.get(2), args.get(3)) {
+        ("list", _, _) => {
+            let mut section = None;
+            for entry in file.entries() {
+                if section != Some(&entry.section) {
+                    output.plain(&format!("[{}]", entry.section));
+                    section = Some(&entry.section);
+                }
+                output.plain(&format!("  {} = {}", entry.key, entry.value));
+            }
+            output.report.config_entries = file.entries().to_vec();
+            return;
+        }
+        ("get", Some(section), Some(key)) => {
+            match file.get(section, key) {
+                Some(entry) => {
+                    print_config_entry(output, entry);
+                    output.report.config_entries.push(entry.clone());
+                }
+                None => output.error(&format!("[{}] {} does not exist in {}", section, key, path.display())),
+            }
+            return;
+        }
+        ("set", Some(section), Some(key)) => file.set(section, key, &args[4..].join(" ")).map(|_| vec![file.get(section, key).unwrap().clone()]),
+        ("reset", Some(section), Some(key)) => file.reset(section, key).map(|_| vec![file.get(section, key).unwrap().clone()]),
+        _ => Ok(file.reset_all()),
+    };
+
+    let changed = match changed.and_then(|x| file.save(&path).map(|_| x)) {
+        Ok(changed) => changed,
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+    for entry in &changed {
+        output.plain(&format!("[{}] {} = {}", entry.section, entry.key, entry.value));
+    }
+    output.success(&format!("{} settings changed in {}!", changed.len(), path.display()));
+    output.report.config_entries = changed;
+}
+
+fn print_config_entry(output: &Output, entry: &CfgEntry) {
+    output.plain(&format!("[{}] {} = {}", entry.section, entry.key, entry.value));
+    for line in &entry.description {
+        output.plain(&format!("  {}", line));
+    }
+    if let Some(setting_type) = &entry.setting_type {
+        output.plain(&format!("  Type: {}", setting_type));
+    }
+    if let Some(default) = &entry.default {
+        output.plain(&format!("  Default: {}", default));
+    }
+    match &entry.acceptable {
+        Some(Acceptable::Values(values)) => output.plain(&format!("  Acceptable values: {}", values.join(", "))),
+        Some(Acceptable::Flags(values)) => output.plain(&format!("  Acceptable values: {}, several separated by commas", values.join(", "))),
+        Some(Acceptable::Range(from, to)) => output.plain(&format!("  Acceptable range: {} to {}", from, to)),
+        None => {}
+    }
+}
+
+fn print_snapshots(output: &mut Output, backup: &Backup) {
+    let snapshots = match backup.list() {
+        Ok(snapshots) => snapshots,
+        Err(err) => {
+            output.failure(&err);
+            return;
+        }
+    };
+    if snapshots.is_empty() {
+        output.warning("No snapshots found!");
+        return;
+    }
+    for snapshot in &snapshots {
+        output.plain(&format!("{} ({} KB)", snapshot.name, snapshot.size / 1024));
+    }
+    output.report.snapshots = snapshots;
+}
+
+fn begin_installation(output: &mut Output, jobs: usize, tui: bool, names: &[String]) {
+    let soulstone_game = match find_game(output) {
+        Some(game) => game,
+        None => return,
+    };
+    check_game_update(output, &soulstone_game, true);
+    let mut installer = Installer::new(&soulstone_game.path);
+    installer.set_jobs(jobs);
+
+    // scripts pick plugins by name, people through the browser or prompts
+    let json_observer = Arc::new(JsonObserver::new(names));
+    let observer: Arc<dyn Observer> = if output.is_json() { json_observer.clone() } else { Arc::new(ConsoleObserver::new(tui).with_names(names)) };
+    installer.set_observer(observer);
 
     let result = installer.install();
-    // Install BepInEx
+    output.report.warnings.append(&mut json_observer.warnings.lock().unwrap());
+    output.report.plugins.append(&mut json_observer.plugins.lock().unwrap());
+    // Install the loader
     if let Err(err) = result {
-        println!("{}", Red.paint(format!("{}", err)));
+        output.failure(&err);
         return;
     }
     let result = result.unwrap();
-    if result.installed_bepinex.is_some() && result.installed_bepinex.unwrap() {
-        println!("{}", Green.paint("BepInEx installed!"));
+    for (plugin, error) in &result.failed {
+        if let Some(report) = output.report.plugins.iter_mut().find(|x| x.name == plugin.name) {
+            report.suggestion = error.suggestion().map(|x| x.to_string());
+        }
+    }
+    let changed = result.installed_bepinex.is_some() && result.installed_bepinex.unwrap();
+    if changed {
+        output.success(&format!("{} installed!", installer.loader().name()));
     } else {
-        println!("{}", Yellow.paint("BepInEx already installed!"));
+        output.warning(&format!("{} already installed!", installer.loader().name()));
+    }
+    let loader = loader_report(&installer, changed);
+    if !loader.ready && loader.state == LoaderState::Enabled {
+        output.warning("Plugins are loaded after the first launch, run first-run or start the game once");
     }
+    output.report.loader = Some(loader);
 
     // Install Plugins
-    if result.plugins.is_some() {
-        let plugins = result.plugins.unwrap();
-        println!("{}", Green.paint(format!("{} Plugins installed!", plugins.len())));
+    if let Some(plugins) = result.plugins {
+        if result.failed.is_empty() {
+            output.success(&format!("{} Plugins installed!", plugins.len()));
+        } else {
+            output.warning(&format!("{} Plugins installed, {} failed", plugins.len(), result.failed.len()));
+        }
     } else {
-        println!("{}", Green.paint("No plugins installed!"));
+        output.success("No plugins installed!");
+    }
+
+    // BepInEx.cfg values from the settings
+    let presets = result.presets;
+    if presets.pending {
+        output.warning("BepInEx.cfg does not exist yet, start the game once and run the installer again to apply the BepInEx.cfg settings");
+    }
+    if !presets.applied.is_empty() {
+        output.success(&format!("BepInEx.cfg updated: {}", presets.applied.join(", ")));
+    }
+    for name in &presets.kept {
+        output.plain(&format!("Kept {}, it was changed by hand", name));
+    }
+    for name in &presets.missing {
+        output.warning(&format!("{} does not exist in BepInEx.cfg", name));
+    }
+    output.report.presets = Some(presets);
+
+    if !output.is_json() {
+        output.info("Run launch to start the game, or launch --vanilla to play without mods");
     }
 }
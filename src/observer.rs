@@ -0,0 +1,60 @@
+use crate::installer::InstallResult;
+use crate::plugin::Plugin;
+use crate::records::Records;
+
+pub enum Step {
+    // backing up and removing another mod loader found in the game folder
+    RemoveConflicts,
+    InstallLoader,
+    FetchPlugins,
+    SelectPlugins,
+    InstallPlugins,
+    // waiting for the game to finish its first launch
+    FirstRun,
+    // downloading the installed version of the loader or a plugin again
+    RepairDownload,
+    // putting the missing and changed files back
+    RepairRestore,
+    // waiting for a vanilla or profile launch to end
+    WaitForExit,
+}
+
+pub enum PluginStatus {
+    Queued,
+    Fetching,
+    Downloading(u64, Option<u64>),
+    Installed(String),
+    Failed(String),
+}
+
+/// Receives everything the installer does instead of it printing or reading input itself.
+/// Every method has a default so a front-end only implements what it shows. Plugin events
+/// are sent from the download threads, hence the `Send + Sync` bound.
+pub trait Observer: Send + Sync {
+    fn step_started(&self, _step: Step) {}
+
+    // progress of a download that does not belong to a plugin, like the loader
+    fn download_progress(&self, _name: &str, _downloaded: u64, _total: Option<u64>) {}
+
+    // `index` is the position of the plugin in the list that is being installed
+    fn plugin_status(&self, _index: usize, _plugin: &Plugin, _status: PluginStatus) {}
+
+    fn warning(&self, _message: &str) {}
+
+    fn completed(&self, _result: &InstallResult) {}
+
+    /// Asks which plugins should be installed, returns their indices.
+    fn select_plugins(&self, _plugins: &[Plugin], _records: &Records) -> Vec<usize> {
+        return Vec::new();
+    }
+
+    /// Asks a yes or no question, nobody answering means no.
+    fn confirm(&self, _question: &str) -> bool {
+        return false;
+    }
+}
+
+/// Ignores every event and declines every prompt.
+pub struct SilentObserver;
+
+impl Observer for SilentObserver {}
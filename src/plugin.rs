@@ -1,114 +1,151 @@
-use std::io::Write;
-use crate::config::USER_AGENT;
-use crate::github_releases::{GithubReleases, Release};
-
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use crate::download::Downloader;
+use crate::error::InstallerError;
+use crate::http::Http;
+use crate::loader::LoaderKind;
+use crate::settings::{Channel, Settings};
+use crate::extract::{Extractor, RejectedEntry};
+use crate::transaction::Transaction;
+use crate::github_releases::{Asset, GithubReleases, Release};
+
+#[derive(Clone)]
 pub struct Plugin {
     pub name: String,
     pub url: String,
     pub repo: String,
     pub description: String,
+    pub stars: i64,
+    pub license: Option<String>,
+    pub(crate) release: OnceLock<Release>,
 }
 
-pub enum PluginError {
-    ReqwestError(reqwest::Error),
-    SerdeError(serde_json::Error),
-    ZipError(zip::result::ZipError),
-    IoError(std::io::Error),
-    PluginError(String),
+pub struct PluginDownload {
+    pub version: String,
+    pub files: Vec<PathBuf>,
+    pub rejected: Vec<RejectedEntry>,
 }
 
-// implement display trait for custom error type
-impl std::fmt::Display for PluginError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            PluginError::ReqwestError(e) => write!(f, "Reqwest Error: {}", e),
-            PluginError::SerdeError(e) => write!(f, "Serde Error: {}", e),
-            PluginError::PluginError(e) => write!(f, "Plugin Error: {}", e),
-            PluginError::ZipError(e) => write!(f, "Zip Error: {}", e),
-            PluginError::IoError(e) => write!(f, "IO Error: {}", e),
-        }
+impl Plugin {
+
+    /// The latest release, once `latest_release` looked it up.
+    pub fn release(&self) -> Option<&Release> {
+        return self.release.get();
     }
-}
 
-impl Plugin {
+    // the release is cached so browsing and downloading only costs one request
+    pub fn latest_release(&self) -> Result<&Release, InstallerError> {
+        return self.fetch_release().map_err(|x| self.error(x));
+    }
 
-    pub fn download(&self, path: &str) -> Result<(), PluginError> {
-        let path = format!("{}/bepinex/plugins", path);
+    pub fn download(&self, game_path: &str, progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<PluginDownload, InstallerError> {
+        return self.download_release(game_path, progress).map_err(|x| self.error(x));
+    }
+
+    // tells which plugin an error belongs to
+    fn error(&self, error: InstallerError) -> InstallerError {
+        return InstallerError::PluginError {
+            plugin: self.name.clone(),
+            source: Box::new(error),
+        };
+    }
+
+    fn fetch_release(&self) -> Result<&Release, InstallerError> {
+        if let Some(release) = self.release.get() {
+            return Ok(release);
+        }
 
         // the url is the html_url from the github api
         // get the latest release from url + /releases/latest
         let url = format!("{}/releases", self.url);
-        let response = reqwest::blocking::Client::new()
-            .get(&url)
-            .header("User-Agent", USER_AGENT)
-            .send()
-            .map_err(PluginError::ReqwestError)?;
-
-        // check if status is 403
-        if response.status() == 403 {
-            println!("Rate limit exceeded, please wait a few minutes and try again.");
-            return Err(PluginError::PluginError("Rate limit exceeded".to_string()));
-        }
-
-        //  error decoding response body: invalid type: map, expected a string at line 2 column 2
-        let response = response.text().map_err(PluginError::ReqwestError)?;
-
-        // invalid type: map, expected a string at line 2 column 2
+        let response = Http::get_api(&url)?;
+        let response = serde_json::from_str::<GithubReleases>(&response).map_err(InstallerError::serde(&url))?;
+
+        // releases are sorted newest first, prereleases only count on the prerelease channel
+        let prerelease = Settings::current().channel == Channel::Prerelease;
+        let first_release = match response.into_iter().find(|x| !x.draft && (prerelease || !x.prerelease)) {
+            Some(release) => release,
+            None => return Err(InstallerError::ValidationError("No release found".to_string())),
+        };
+
+        // another thread might have been faster, either release is fine
+        return Ok(self.release.get_or_init(|| first_release));
+    }
 
-        let response = serde_json::from_str::<GithubReleases>(&response);
-        if response.is_err() {
-            return Err(PluginError::SerdeError(response.err().unwrap()));
-        }
+    fn download_release(&self, game_path: &str, progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<PluginDownload, InstallerError> {
+        // the folder the loader scans, spelled like the loader does so case sensitive file systems find it
+        let path = Path::new(game_path).join(LoaderKind::active().plugins_path());
 
-        let response = response.unwrap();
+        let first_release = self.fetch_release()?;
+        let asset = Plugin::release_asset(first_release)?;
 
-        // get first asset from release that contains the name 'Skada'
-        let first_release = response
-            .first()
-            .unwrap();
+        // stage the plugin first so a failed download never leaves half a plugin behind, the transaction
+        // works in the download folder of the plugin so parallel downloads never clean up each others folders
+        let downloads_path = Downloader::folder(Path::new(game_path), &self.name, &asset.browser_download_url);
+        let transaction = Transaction::new(&path, &downloads_path).map_err(InstallerError::io(&downloads_path))?;
 
-        let asset = first_release
-            .assets
-            .first();
+        // download asset, the download folder is kept so an interrupted download can be resumed
+        let file_path = downloads_path.join(&asset.name);
+        Downloader::download(&asset.browser_download_url, &file_path, Some(asset.size as u64), progress)?;
 
-        if asset.is_none() {
-            return Err(PluginError::PluginError("No asset found".to_string()));
+        // unzip asset if it is a zip file, otherwise stage the dll as is
+        let mut rejected = Vec::new();
+        if asset.name.ends_with(".zip") {
+            let archive = std::fs::File::open(&file_path).map_err(InstallerError::io(&file_path))?;
+            let mut archive = zip::ZipArchive::new(archive).map_err(InstallerError::zip(&file_path))?;
+            let extracted = Extractor::extract(&mut archive, &transaction.staging_path()).map_err(InstallerError::zip(&file_path))?;
+            rejected = extracted.rejected;
+            drop(archive);
+            std::fs::remove_file(&file_path).map_err(InstallerError::io(&file_path))?;
+        } else {
+            std::fs::rename(&file_path, transaction.staging_path().join(&asset.name)).map_err(InstallerError::io(&file_path))?;
         }
 
-        let asset = asset.unwrap();
+        // move the plugin into the plugins folder, replaced files are restored if this fails
+        let files = transaction.commit().map_err(InstallerError::io(&path))?;
+
+        return Ok(PluginDownload {
+            version: first_release.tag_name.clone(),
+            files,
+            rejected,
+        });
+    }
 
-        // download asset
-        println!("Found Release");
-        let response = reqwest::blocking::Client::new()
-            .get(&asset.browser_download_url)
-            .header("User-Agent", USER_AGENT)
-            .send()
-            .map_err(PluginError::ReqwestError)?;
+    // the file of a release that holds the plugin
+    fn release_asset(release: &Release) -> Result<&Asset, InstallerError> {
+        let asset = match release.assets.first() {
+            Some(asset) => asset,
+            None => return Err(InstallerError::ValidationError(format!("Release {} has no files", release.tag_name))),
+        };
 
         // make sure path ends with .dll or .zip
         if !asset.name.ends_with(".dll") && !asset.name.ends_with(".zip") {
-            return Err(PluginError::PluginError("Asset is not a dll or zip file".to_string()));
+            return Err(InstallerError::ValidationError(format!("{} is not a dll or zip file", asset.name)));
         }
 
-        if !std::path::Path::new(&path).exists() {
-            std::fs::create_dir_all(&path).unwrap();
-        }
-
-        let file_path = format!("{}/{}", path, asset.name);
-        // save asset to path
-        let mut file = std::fs::File::create(&file_path).unwrap();
-        file.write_all(&response.bytes().unwrap()).unwrap();
-
+        return Ok(asset);
+    }
 
-        // unzip asset if it is a zip file
+    /// Downloads the release `version` of the plugin at `repo` and unpacks it into `target`, laid out like
+    /// the plugins folder of the loader. Repairs use it to get back the exact files that were installed.
+    pub(crate) fn fetch_version(repo: &str, version: &str, target: &Path, progress: &mut dyn FnMut(u64, Option<u64>)) -> Result<(), InstallerError> {
+        // the repo is the html url, the api lives next to it
+        let url = format!("{}/releases/tags/{}", repo.replacen("https://github.com/", "https://api.github.com/repos/", 1), version);
+        let response = Http::get_api(&url)?;
+        let release = serde_json::from_str::<Release>(&response).map_err(InstallerError::serde(&url))?;
+        let asset = Plugin::release_asset(&release)?;
+
+        std::fs::create_dir_all(target).map_err(InstallerError::io(target))?;
+        let file_path = target.join(&asset.name);
+        Downloader::download(&asset.browser_download_url, &file_path, Some(asset.size as u64), progress)?;
         if asset.name.ends_with(".zip") {
-            let mut archive = zip::ZipArchive::new( std::fs::File::open(&file_path).unwrap()).unwrap();
-            archive.extract(path).map_err(PluginError::ZipError)?;
-            // delete zip file
-            std::fs::remove_file(file_path).unwrap();
+            let archive = std::fs::File::open(&file_path).map_err(InstallerError::io(&file_path))?;
+            let mut archive = zip::ZipArchive::new(archive).map_err(InstallerError::zip(&file_path))?;
+            Extractor::extract(&mut archive, target).map_err(InstallerError::zip(&file_path))?;
+            drop(archive);
+            std::fs::remove_file(&file_path).map_err(InstallerError::io(&file_path))?;
         }
 
-
         return Ok(());
     }
-}
\ No newline at end of file
+}
@@ -1,12 +1,17 @@
-use crate::config::USER_AGENT;
-use crate::github_repositories::{GithubRepositories, Repository};
+use std::sync::OnceLock;
+use crate::error::InstallerError;
+use crate::http::Http;
+use crate::github_repositories::GithubRepositories;
 use crate::plugin::Plugin;
+use crate::settings::Settings;
 
 pub struct Plugins;
 
 impl Plugins {
 
-    fn get_repositories() -> Result<GithubRepositories, reqwest::Error> {
+    // the users endpoint lists the repositories of organizations too
+    fn get_repositories(source: &str) -> Result<GithubRepositories, InstallerError> {
+        let url = format!("https://api.github.com/users/{}/repos?per_page=100", source);
         // X-Originating-IP: 127.0.0.1
         // X-Forwarded-For: 127.0.0.1
         // X-Remote-IP: 127.0.0.1
@@ -14,25 +19,18 @@ impl Plugins {
         // X-Client-IP: 127.0.0.1
         // X-Host: 127.0.0.1
         // X-Forwared-Host: 127.0.0.1
-        let response = reqwest::blocking::Client::new()
-            .get("https://api.github.com/orgs/SoulstoneAddons/repos")
-            .header("User-Agent", USER_AGENT)
-            .send()?;
-
-        // check if status is 403
-        if response.status() == 403 {
-            println!("Rate limit exceeded, please wait a few minutes and try again.");
-            std::process::exit(1);
-        }
-
-        let response = response.json::<GithubRepositories>()?;
+        let response = Http::get_api(&url)?;
+        let response = serde_json::from_str::<GithubRepositories>(&response).map_err(InstallerError::serde(&url))?;
 
         return Ok(response);
     }
 
-    pub fn get_plugins() -> Result<Vec<Plugin>, reqwest::Error> {
-        let plugins = Plugins::get_repositories()?;
-        let plugins = plugins
+    pub fn get_plugins() -> Result<Vec<Plugin>, InstallerError> {
+        let mut repositories = Vec::new();
+        for source in &Settings::current().sources {
+            repositories.extend(Plugins::get_repositories(source)?);
+        }
+        let plugins = repositories
             .into_iter()
             .filter(|x| !x.private && x.topics.contains(&"plugin".to_string()))
             .collect::<Vec<_>>()
@@ -42,6 +40,9 @@ impl Plugins {
                 description: x.description.clone().unwrap_or("".to_string()),
                 url: x.url.clone(),
                 repo: x.html_url.clone(),
+                stars: x.stargazers_count,
+                license: x.license.as_ref().and_then(|x| x.spdx_id.clone().or(x.name.clone())),
+                release: OnceLock::new(),
             })
             .collect::<Vec<_>>();
 
@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::InstallerError;
+use crate::installer::Installer;
+use crate::loader::LoaderKind;
+use crate::records::Records;
+use crate::transaction::Transaction;
+
+/// Named sets of enabled plugins with their config files, stored in `<game>/BepInEx-profiles/<name>`.
+/// Only plugins installed by this installer are switched, anything added by hand stays as it is.
+pub struct Profiles {
+    path: String,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    // plugins that are enabled in this profile, every other tracked plugin is disabled
+    pub plugins: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct SwitchResult {
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+    pub configs: usize,
+}
+
+impl Profiles {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    fn profiles_path(&self) -> PathBuf {
+        return Path::new(&self.path).join("BepInEx-profiles");
+    }
+
+    // where the plugin settings of the active loader live, a profile keeps its own copy
+    fn config_path(&self) -> &'static str {
+        return LoaderKind::active().config_path();
+    }
+
+    fn profile_path(&self, name: &str) -> Result<PathBuf, InstallerError> {
+        // the name becomes a folder name
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_' || x == '.' || x == ' ');
+        if !valid {
+            return Err(InstallerError::ValidationError(format!("{} is not a valid profile name, use letters, numbers, spaces, - and _", name)));
+        }
+
+        return Ok(self.profiles_path().join(name));
+    }
+
+    pub fn list(&self) -> Result<Vec<Profile>, InstallerError> {
+        let profiles_path = self.profiles_path();
+        let mut profiles = Vec::new();
+        if !profiles_path.exists() {
+            return Ok(profiles);
+        }
+
+        for entry in std::fs::read_dir(&profiles_path).map_err(InstallerError::io(&profiles_path))? {
+            let entry = entry.map_err(InstallerError::io(&profiles_path))?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Ok(profile) = self.load(&name) {
+                profiles.push(profile);
+            }
+        }
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+
+        return Ok(profiles);
+    }
+
+    pub fn load(&self, name: &str) -> Result<Profile, InstallerError> {
+        let path = self.profile_path(name)?.join("profile.json");
+        if !path.exists() {
+            return Err(InstallerError::ValidationError(format!("Profile {} not found", name)));
+        }
+        let contents = std::fs::read_to_string(&path).map_err(InstallerError::io(&path))?;
+
+        return serde_json::from_str(&contents).map_err(|x| {
+            InstallerError::ValidationError(format!("{} is not a valid profile: {}", path.display(), x))
+        });
+    }
+
+    /// The profile that was switched to last.
+    pub fn active(&self) -> Option<String> {
+        return Records::load(&self.path).profile;
+    }
+
+    /// Saves the enabled plugins and the config files as they are now, an existing profile is replaced.
+    pub fn save(&self, name: &str) -> Result<Profile, InstallerError> {
+        let profile_path = self.profile_path(name)?;
+        let records = Records::load(&self.path);
+        let profile = Profile {
+            name: name.to_string(),
+            plugins: records.plugins.iter().filter(|x| !x.disabled).map(|x| x.name.clone()).collect(),
+        };
+
+        std::fs::create_dir_all(&profile_path).map_err(InstallerError::io(&profile_path))?;
+        let temp_path = Path::new(&self.path).join("temp");
+        let mut transaction = Transaction::new(&profile_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+        let config_path = Path::new(&self.path).join(self.config_path());
+        Profiles::stage_folder(&mut transaction, &profile_path, &config_path, Path::new("config"))?;
+        let contents = serde_json::to_vec_pretty(&profile).map_err(std::io::Error::from).map_err(InstallerError::io(&profile_path))?;
+        let staged = transaction.staging_path().join("profile.json");
+        std::fs::write(&staged, contents).map_err(InstallerError::io(&staged))?;
+        transaction.commit().map_err(InstallerError::io(&profile_path))?;
+
+        // saving the current setup makes it the active profile
+        let mut records = Records::load(&self.path);
+        records.profile = Some(name.to_string());
+        records.save(&self.path)?;
+
+        return Ok(profile);
+    }
+
+    /// Enables the plugins of the profile, disables every other tracked plugin and swaps in its config files.
+    /// The config files of the profile that was active before are saved back into it first.
+    pub fn switch(&self, name: &str) -> Result<SwitchResult, InstallerError> {
+        let profile = self.load(name)?;
+        let mut records = Records::load(&self.path);
+
+        // keep the changes made while the previous profile was active
+        if let Some(active) = records.profile.clone().filter(|x| x != name) {
+            if self.load(&active).is_ok() {
+                let active_path = self.profile_path(&active)?;
+                let temp_path = Path::new(&self.path).join("temp");
+                let mut transaction = Transaction::new(&active_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+                Profiles::stage_folder(&mut transaction, &active_path, &Path::new(&self.path).join(self.config_path()), Path::new("config"))?;
+                transaction.commit().map_err(InstallerError::io(&active_path))?;
+            }
+        }
+
+        // move plugins and config files in one go so a failure leaves the old profile intact
+        let game_path = Path::new(&self.path);
+        let temp_path = game_path.join("temp");
+        let mut transaction = Transaction::new(game_path, &temp_path).map_err(InstallerError::io(&temp_path))?;
+        let mut result = SwitchResult::default();
+        let mut updated = Vec::new();
+        for record in &records.plugins {
+            let enabled = profile.plugins.iter().any(|x| x.eq_ignore_ascii_case(&record.name));
+            if enabled != record.disabled {
+                updated.push(record.clone());
+                continue;
+            }
+            updated.push(Installer::stage_plugin_state(&mut transaction, game_path, record, enabled)?);
+            if enabled {
+                result.enabled.push(record.name.clone());
+            } else {
+                result.disabled.push(record.name.clone());
+            }
+        }
+        let profile_config = self.profile_path(name)?.join("config");
+        result.configs = Profiles::stage_folder(&mut transaction, game_path, &profile_config, Path::new(self.config_path()))?;
+        transaction.commit().map_err(InstallerError::io(game_path))?;
+
+        records.plugins = updated;
+        records.profile = Some(name.to_string());
+        records.save(&self.path)?;
+
+        return Ok(result);
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), InstallerError> {
+        let profile_path = self.profile_path(name)?;
+        self.load(name)?;
+        std::fs::remove_dir_all(&profile_path).map_err(InstallerError::io(&profile_path))?;
+
+        // a deleted profile can not be active
+        let mut records = Records::load(&self.path);
+        if records.profile.as_deref() == Some(name) {
+            records.profile = None;
+            records.save(&self.path)?;
+        }
+
+        return Ok(());
+    }
+
+    // stages `source` so `relative` in the target becomes an exact copy of it, returns how many files it holds
+    fn stage_folder(transaction: &mut Transaction, target: &Path, source: &Path, relative: &Path) -> Result<usize, InstallerError> {
+        let mut files = Vec::new();
+        Transaction::collect_files(source, Path::new(""), &mut files).map_err(InstallerError::io(source))?;
+        for file in &files {
+            transaction.stage_file(&source.join(file), &relative.join(file)).map_err(InstallerError::io(source.join(file)))?;
+        }
+
+        let keep = files.iter().map(|x| relative.join(x)).collect::<HashSet<_>>();
+        let mut current = Vec::new();
+        Transaction::collect_files(target, relative, &mut current).map_err(InstallerError::io(target.join(relative)))?;
+        for file in current {
+            if !keep.contains(&file) {
+                transaction.remove(&file);
+            }
+        }
+
+        return Ok(files.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::PluginRecord;
+
+    #[test]
+    fn switch_swaps_plugins_and_configs_and_saves_the_previous_profile() {
+        let game = std::env::temp_dir().join(format!("bepinex_installer-profiles-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&game);
+        let path = game.to_string_lossy().to_string();
+        let profiles = Profiles::new(&path);
+        let config = game.join(profiles.config_path());
+        std::fs::create_dir_all(game.join("BepInEx/plugins")).unwrap();
+        std::fs::create_dir_all(&config).unwrap();
+        let mut records = Records::default();
+        for name in ["A", "B"] {
+            let file = format!("BepInEx/plugins/{}.dll", name);
+            std::fs::write(game.join(&file), name).unwrap();
+            records.plugins.push(PluginRecord {
+                name: name.to_string(),
+                version: "1.0.0".to_string(),
+                files: vec![file],
+                ..Default::default()
+            });
+        }
+        records.save(&path).unwrap();
+        std::fs::write(config.join("A.cfg"), "a = 1").unwrap();
+        profiles.save("all").unwrap();
+
+        Installer::new(&path).set_plugin_enabled("B", false).unwrap();
+        std::fs::write(config.join("A.cfg"), "a = 2").unwrap();
+        std::fs::write(config.join("B.cfg"), "b = 1").unwrap();
+        let solo = profiles.save("solo").unwrap();
+        assert_eq!(solo.plugins, vec!["A"]);
+
+        // changed after saving, switching away keeps it in the profile
+        std::fs::write(config.join("A.cfg"), "a = 3").unwrap();
+        let result = profiles.switch("all").unwrap();
+        assert_eq!(result.enabled, vec!["B"]);
+        assert!(result.disabled.is_empty());
+        assert_eq!(result.configs, 1);
+        assert_eq!(std::fs::read_to_string(game.join("BepInEx/plugins/B.dll")).unwrap(), "B");
+        assert_eq!(std::fs::read_to_string(config.join("A.cfg")).unwrap(), "a = 1");
+        assert!(!config.join("B.cfg").exists());
+        assert_eq!(profiles.active().as_deref(), Some("all"));
+
+        let result = profiles.switch("solo").unwrap();
+        assert!(result.enabled.is_empty());
+        assert_eq!(result.disabled, vec!["B"]);
+        assert_eq!(result.configs, 2);
+        assert!(!game.join("BepInEx/plugins/B.dll").exists());
+        assert_eq!(std::fs::read_to_string(game.join(PluginRecord::disabled_path("BepInEx/plugins/B.dll"))).unwrap(), "B");
+        assert_eq!(std::fs::read_to_string(config.join("A.cfg")).unwrap(), "a = 3");
+        assert_eq!(std::fs::read_to_string(config.join("B.cfg")).unwrap(), "b = 1");
+        assert_eq!(std::fs::read_to_string(profiles.profile_path("all").unwrap().join("config/A.cfg")).unwrap(), "a = 1");
+        assert!(Records::load(&path).find("B").unwrap().disabled);
+        std::fs::remove_dir_all(&game).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// console rendering of the download progress the installer reports to the observer
+const BAR_WIDTH: usize = 30;
+// redraw at most this often, drawing every chunk slows the download down
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct ProgressBar {
+    label: String,
+    started: Instant,
+    last_draw: Option<Instant>,
+    // bytes that were already on disk when the download started
+    resumed: Option<u64>,
+}
+
+impl ProgressBar {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            started: Instant::now(),
+            last_draw: None,
+            resumed: None,
+        }
+    }
+
+    pub fn update(&mut self, downloaded: u64, total: Option<u64>) {
+        let done = total.map(|total| downloaded >= total).unwrap_or(false);
+        if let Some(last_draw) = self.last_draw {
+            if !done && last_draw.elapsed() < REDRAW_INTERVAL {
+                return;
+            }
+        }
+        self.last_draw = Some(Instant::now());
+
+        // clear the line and draw over it
+        let line = self.render(downloaded, total);
+        print!("\r\x1b[2K{}", line);
+        let _ = std::io::stdout().flush();
+    }
+
+    pub fn render(&mut self, downloaded: u64, total: Option<u64>) -> String {
+        let resumed = *self.resumed.get_or_insert(downloaded);
+
+        // speed only counts bytes downloaded by this session
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            downloaded.saturating_sub(resumed) as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        return match total {
+            Some(total) if total > 0 => {
+                let ratio = (downloaded as f64 / total as f64).min(1.0);
+                let filled = (ratio * BAR_WIDTH as f64) as usize;
+                let eta = if speed > 0.0 {
+                    format_duration((total.saturating_sub(downloaded)) as f64 / speed)
+                } else {
+                    "--".to_string()
+                };
+                format!(
+                    "{} [{}{}] {:>3}% {}/{} {}/s ETA {}",
+                    self.label,
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH - filled),
+                    (ratio * 100.0) as u32,
+                    format_bytes(downloaded),
+                    format_bytes(total),
+                    format_bytes(speed as u64),
+                    eta,
+                )
+            }
+            // without a size there is nothing to fill the bar with
+            _ => format!("{} {} {}/s", self.label, format_bytes(downloaded), format_bytes(speed as u64)),
+        };
+    }
+
+    pub fn finish(&mut self) {
+        println!();
+    }
+}
+
+/// Draws one line per download and redraws all of them in place.
+/// Safe to share between the threads that run the downloads.
+pub struct MultiProgress {
+    state: Mutex<MultiState>,
+}
+
+struct MultiState {
+    bars: Vec<ProgressBar>,
+    lines: Vec<String>,
+    // how many lines are currently on screen
+    drawn: usize,
+    last_draw: Option<Instant>,
+}
+
+impl Default for MultiProgress {
+    fn default() -> Self {
+        return MultiProgress::new();
+    }
+}
+
+impl MultiProgress {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(MultiState {
+                bars: Vec::new(),
+                lines: Vec::new(),
+                drawn: 0,
+                last_draw: None,
+            }),
+        }
+    }
+
+    pub fn add(&self, label: &str, message: &str) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.bars.push(ProgressBar::new(label));
+        state.lines.push(format!("{} {}", label, message));
+        MultiProgress::draw(state);
+        return state.lines.len() - 1;
+    }
+
+    pub fn set_message(&self, index: usize, message: &str) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.lines[index] = format!("{} {}", state.bars[index].label, message);
+        MultiProgress::draw(state);
+    }
+
+    pub fn update(&self, index: usize, downloaded: u64, total: Option<u64>) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.lines[index] = state.bars[index].render(downloaded, total);
+        if let Some(last_draw) = state.last_draw {
+            if last_draw.elapsed() < REDRAW_INTERVAL {
+                return;
+            }
+        }
+        MultiProgress::draw(state);
+    }
+
+    fn draw(state: &mut MultiState) {
+        let mut output = String::new();
+        // move back up to the first line we drew
+        if state.drawn > 0 {
+            output.push_str(&format!("\x1b[{}A", state.drawn));
+        }
+        for line in &state.lines {
+            output.push_str(&format!("\r\x1b[2K{}\n", line));
+        }
+        print!("{}", output);
+        let _ = std::io::stdout().flush();
+
+        state.drawn = state.lines.len();
+        state.last_draw = Some(Instant::now());
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    return if unit == 0 {
+        format!("{} {}", bytes, units[unit])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    };
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    return if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    };
+}
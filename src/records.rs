@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::InstallerError;
+use crate::loader::LoaderKind;
+
+/// What the installer put into the game folder, stored in `BepInEx/installer.json`.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Records {
+    // version of the loader, only known when this installer put it there
+    pub loader: Option<String>,
+    // profile that was switched to last
+    pub profile: Option<String>,
+    // steam build of the game the mods were set up for
+    pub game_build: Option<String>,
+    pub plugins: Vec<PluginRecord>,
+    // sha256 of every file the loader zip put into the game folder
+    #[serde(default)]
+    pub loader_files: BTreeMap<String, String>,
+    // BepInEx.cfg values we wrote, a different value in the file means the user changed it since
+    #[serde(default)]
+    pub bepinex_cfg: BTreeMap<String, BTreeMap<String, String>>,
+    // what the running launch changed, kept here so an interrupted launch is put back next time
+    pub launch: Option<LaunchRestore>,
+}
+
+/// What `launch` changed for the game it started, put back once the game exits.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchRestore {
+    // whether the loader was enabled before, only set when the launch changed it
+    pub loader_enabled: Option<bool>,
+    // the profile to switch back to
+    pub profile: Option<String>,
+    // the profile was saved just for the launch and is deleted again
+    #[serde(default)]
+    pub temporary_profile: bool,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct PluginRecord {
+    pub name: String,
+    pub version: String,
+    pub repo: String,
+    // paths relative to the game folder, always with forward slashes
+    pub files: Vec<String>,
+    // disabled plugins keep their files in the disabled folder of their loader
+    #[serde(default)]
+    pub disabled: bool,
+    // sha256 of every file, by the path in `files`
+    #[serde(default)]
+    pub hashes: BTreeMap<String, String>,
+}
+
+impl PluginRecord {
+    /// Where the files of the plugin are right now.
+    pub fn current_files(&self) -> Vec<String> {
+        if !self.disabled {
+            return self.files.clone();
+        }
+
+        return self.files.iter().map(|x| PluginRecord::disabled_path(x)).collect();
+    }
+
+    // `BepInEx/plugins/x` becomes `BepInEx/disabled/x` and `Mods/x` becomes `MelonLoader/Disabled/x`, the loaders never look there
+    pub fn disabled_path(file: &str) -> String {
+        return match LoaderKind::plugin_file(file) {
+            Some((loader, rest)) => format!("{}/{}", loader.disabled_path(), rest),
+            None => format!("BepInEx/disabled/{}", file),
+        };
+    }
+}
+
+impl LaunchRestore {
+    pub fn is_empty(&self) -> bool {
+        return self.loader_enabled.is_none() && self.profile.is_none();
+    }
+}
+
+impl Records {
+    pub(crate) fn records_path(game_path: &str) -> PathBuf {
+        return Path::new(game_path).join("BepInEx").join("installer.json");
+    }
+
+    // missing or unreadable records are treated as an empty install
+    pub fn load(game_path: &str) -> Self {
+        let contents = std::fs::read_to_string(Records::records_path(game_path));
+        if contents.is_err() {
+            return Self::default();
+        }
+
+        return serde_json::from_str(&contents.unwrap()).unwrap_or_default();
+    }
+
+    pub fn save(&self, game_path: &str) -> Result<(), InstallerError> {
+        let path = Records::records_path(game_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(InstallerError::io(parent))?;
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(std::io::Error::from).map_err(InstallerError::io(&path))?;
+
+        return std::fs::write(&path, contents).map_err(InstallerError::io(&path));
+    }
+
+    pub fn find(&self, name: &str) -> Option<&PluginRecord> {
+        return self.plugins.iter().find(|x| x.name.eq_ignore_ascii_case(name));
+    }
+
+    // replaces the record of a plugin with the same name
+    pub fn insert(&mut self, record: PluginRecord) {
+        self.plugins.retain(|x| !x.name.eq_ignore_ascii_case(&record.name));
+        self.plugins.push(record);
+    }
+
+    pub fn relative_path(game_path: &str, path: &Path) -> String {
+        let relative = path.strip_prefix(game_path).unwrap_or(path);
+        return relative
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+    }
+}
@@ -0,0 +1,237 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use ansi_term::Color::{Green, Red, Yellow};
+use ansi_term::Colour;
+use serde::Serialize;
+use bepinex_installer::{CfgEntry, Diagnosis, GameUpdate, InstallerError, LaunchMode, LaunchRestore, LoaderConflict, LoaderKind, LoaderState, LogEntry, LogProblem, Observer, Plugin, PluginRecord, PluginStatus, PluginUpdate, PresetResult, Records, Profile, RepairResult, RestoreResult, Settings, Snapshot, SteamGame, SwitchResult, VerifyResult};
+
+/// Everything a command found out, printed as json with `--json`.
+#[derive(Default, Serialize)]
+pub struct Report {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steam_path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub games: Vec<SteamGame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game: Option<SteamGame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loader: Option<LoaderReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginReport>,
+    // the BepInEx.cfg values from the settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presets: Option<PresetResult>,
+    // plugins this installer put into the game folder
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub installed: Vec<PluginRecord>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub snapshots: Vec<Snapshot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored: Option<RestoreResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<PluginRecord>,
+    // the plugin that was enabled or disabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toggled: Option<PluginRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<Settings>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<Profile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub switched: Option<SwitchResult>,
+    // the `.cfg` files in BepInEx/config
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub config_files: Vec<String>,
+    // the settings that were listed, read or changed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub config_entries: Vec<CfgEntry>,
+    // the shown part of the loader log
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub log_entries: Vec<LogEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub log_problems: Vec<LogProblem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnosis: Option<Diagnosis>,
+    // the zip written by `doctor`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<PathBuf>,
+    // the game build changed since the mods were set up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_update: Option<GameUpdate>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub plugin_updates: Vec<PluginUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launched: Option<LaunchMode>,
+    // what a vanilla or profile launch changed and put back after the game exited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub launch_restored: Option<LaunchRestore>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<VerifyResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repaired: Option<RepairResult>,
+    // files of other mod loaders found in the game folder
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<LoaderConflict>,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct LoaderReport {
+    pub kind: LoaderKind,
+    pub installed: bool,
+    pub state: LoaderState,
+    // only known when this installer put the loader there
+    pub version: Option<String>,
+    // the first launch generated the interop assemblies, plugins are only loaded after that
+    pub ready: bool,
+    // whether this run installed it
+    pub changed: bool,
+}
+
+#[derive(Serialize)]
+pub struct PluginReport {
+    pub name: String,
+    pub installed: bool,
+    pub version: Option<String>,
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+/// Prints messages for people, or collects them into a report for scripts.
+pub struct Output {
+    json: bool,
+    pub report: Report,
+}
+
+impl Output {
+    pub fn new(json: bool) -> Self {
+        Self {
+            json,
+            report: Report::default(),
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        return self.json;
+    }
+
+    pub fn info(&self, message: &str) {
+        if !self.json {
+            println!("{}", Colour::RGB(128, 128, 128).paint(message));
+        }
+    }
+
+    pub fn success(&self, message: &str) {
+        if !self.json {
+            println!("{}", Green.paint(message));
+        }
+    }
+
+    pub fn plain(&self, message: &str) {
+        if !self.json {
+            println!("{}", message);
+        }
+    }
+
+    pub fn warning(&mut self, message: &str) {
+        if self.json {
+            self.report.warnings.push(message.to_string());
+        } else {
+            println!("{}", Yellow.paint(message));
+        }
+    }
+
+    pub fn error(&mut self, message: &str) {
+        if self.json {
+            self.report.error = Some(message.to_string());
+        } else {
+            println!("{}", Red.paint(message));
+        }
+    }
+
+    // an error together with what to do about it
+    pub fn failure(&mut self, error: &InstallerError) {
+        self.error(&format!("{}", error));
+        if let Some(suggestion) = error.suggestion() {
+            self.report.suggestion = Some(suggestion.to_string());
+            self.info(suggestion);
+        }
+    }
+
+    // prints the report, only does something in json mode
+    pub fn finish(&self) {
+        if !self.json {
+            return;
+        }
+
+        match serde_json::to_string_pretty(&self.report) {
+            Ok(json) => println!("{}", json),
+            Err(err) => println!("{{\"error\": \"{}\"}}", err),
+        }
+    }
+}
+
+/// Collects installer events for the report, plugins are picked by name instead of prompting.
+pub struct JsonObserver {
+    names: Vec<String>,
+    pub warnings: Mutex<Vec<String>>,
+    pub plugins: Mutex<Vec<PluginReport>>,
+}
+
+impl JsonObserver {
+    // without names the default plugins from the settings are installed
+    pub fn new(names: &[String]) -> Self {
+        let names = if names.is_empty() { &Settings::current().default_plugins } else { names };
+        Self {
+            names: names.to_vec(),
+            warnings: Mutex::new(Vec::new()),
+            plugins: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Observer for JsonObserver {
+    fn plugin_status(&self, index: usize, plugin: &Plugin, status: PluginStatus) {
+        let mut plugins = self.plugins.lock().unwrap();
+        match status {
+            PluginStatus::Queued => {
+                // queued in order, so the index is the position in the list
+                plugins.push(PluginReport {
+                    name: plugin.name.clone(),
+                    installed: false,
+                    version: None,
+                    error: None,
+                    suggestion: None,
+                });
+            }
+            PluginStatus::Installed(version) => {
+                plugins[index].installed = true;
+                plugins[index].version = Some(version);
+            }
+            PluginStatus::Failed(error) => plugins[index].error = Some(error),
+            _ => {}
+        }
+    }
+
+    fn warning(&self, message: &str) {
+        self.warnings.lock().unwrap().push(message.to_string());
+    }
+
+    fn select_plugins(&self, plugins: &[Plugin], _records: &Records) -> Vec<usize> {
+        let mut selected = Vec::new();
+        for name in &self.names {
+            match plugins.iter().position(|x| x.name.eq_ignore_ascii_case(name)) {
+                Some(index) => selected.push(index),
+                None => self.warning(&format!("Plugin {} not found", name)),
+            }
+        }
+
+        return selected;
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use crate::error::InstallerError;
+use crate::loader::LoaderKind;
+
+// settings of this process, set once at startup
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// User settings, read from `config.json` in the platform config directory.
+/// Environment variables override the file and command line flags override both.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // use this game folder instead of looking through steam
+    pub game_path: Option<String>,
+    // sent with every github api request, raises the rate limit
+    pub github_token: Option<String>,
+    // github users or organizations whose plugin repositories are offered
+    pub sources: Vec<String>,
+    pub channel: Channel,
+    // the mod loader to install, BepInEx when not set
+    pub loader: Option<LoaderKind>,
+    // how long github api responses are reused, 0 turns the cache off
+    pub cache_ttl: u64,
+    pub proxy: Option<String>,
+    // plugins that are selected from the start
+    pub default_plugins: Vec<String>,
+    // BepInEx.cfg values by section and key, applied once the file exists unless the user changed them
+    pub bepinex_cfg: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Stable,
+    Prerelease,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            game_path: None,
+            github_token: None,
+            sources: vec!["SoulstoneAddons".to_string()],
+            channel: Channel::Stable,
+            loader: None,
+            cache_ttl: 600,
+            proxy: None,
+            default_plugins: Vec::new(),
+            bepinex_cfg: BTreeMap::new(),
+        }
+    }
+}
+
+impl std::str::FromStr for Channel {
+    type Err = InstallerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return match value.to_lowercase().as_str() {
+            "stable" => Ok(Channel::Stable),
+            "prerelease" => Ok(Channel::Prerelease),
+            _ => Err(InstallerError::ValidationError(format!("Unknown release channel {}, use stable or prerelease", value))),
+        };
+    }
+}
+
+impl Settings {
+    /// `<config dir>/bepinex_installer/config.json`
+    pub fn path() -> Option<PathBuf> {
+        return dirs::config_dir().map(|x| x.join("bepinex_installer").join("config.json"));
+    }
+
+    /// Reads the config file, a missing file means the defaults.
+    pub fn load() -> Result<Self, InstallerError> {
+        let path = match Settings::path() {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path).map_err(InstallerError::io(&path))?;
+        return serde_json::from_str(&contents).map_err(|x| {
+            InstallerError::ValidationError(format!("{} is not a valid config file: {}", path.display(), x))
+        });
+    }
+
+    pub fn save(&self) -> Result<(), InstallerError> {
+        let path = match Settings::path() {
+            Some(path) => path,
+            None => return Err(InstallerError::ValidationError("No config directory on this system".to_string())),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(InstallerError::io(parent))?;
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(std::io::Error::from).map_err(InstallerError::io(&path))?;
+
+        return std::fs::write(&path, contents).map_err(InstallerError::io(&path));
+    }
+
+    /// Applies the `BEPINEX_INSTALLER_*` environment variables, `GITHUB_TOKEN` is used too.
+    pub fn apply_env(&mut self) -> Result<(), InstallerError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|x| !x.is_empty());
+
+        if let Some(value) = var("BEPINEX_INSTALLER_GAME_PATH") {
+            self.game_path = Some(value);
+        }
+        if let Some(value) = var("BEPINEX_INSTALLER_GITHUB_TOKEN").or_else(|| var("GITHUB_TOKEN")) {
+            self.github_token = Some(value);
+        }
+        if let Some(value) = var("BEPINEX_INSTALLER_SOURCES") {
+            self.sources = value.split(',').map(|x| x.trim().to_string()).filter(|x| !x.is_empty()).collect();
+        }
+        if let Some(value) = var("BEPINEX_INSTALLER_CHANNEL") {
+            self.channel = value.parse()?;
+        }
+        if let Some(value) = var("BEPINEX_INSTALLER_LOADER") {
+            self.loader = Some(value.parse()?);
+        }
+        if let Some(value) = var("BEPINEX_INSTALLER_CACHE_TTL") {
+            self.cache_ttl = value.parse().map_err(|_| {
+                InstallerError::ValidationError(format!("BEPINEX_INSTALLER_CACHE_TTL expects seconds, got {}", value))
+            })?;
+        }
+        if let Some(value) = var("BEPINEX_INSTALLER_PROXY") {
+            self.proxy = Some(value);
+        }
+
+        return Ok(());
+    }
+
+    /// Adds a BepInEx.cfg value given as `Section.Key=value`, the key is everything after the last dot.
+    pub fn add_bepinex_cfg(&mut self, value: &str) -> Result<(), InstallerError> {
+        let parsed = value.split_once('=').and_then(|(name, value)| {
+            let (section, key) = name.trim().rsplit_once('.')?;
+            return Some((section.to_string(), key.to_string(), value.trim().to_string()));
+        });
+
+        return match parsed {
+            Some((section, key, value)) if !section.is_empty() && !key.is_empty() => {
+                self.bepinex_cfg.entry(section).or_default().insert(key, value);
+                Ok(())
+            }
+            _ => Err(InstallerError::ValidationError(format!("--cfg expects Section.Key=value, got {}", value))),
+        };
+    }
+
+    /// Makes these settings the ones every request and lookup uses.
+    /// Only the first call has an effect, returns whether it was this one.
+    pub fn apply(self) -> bool {
+        return SETTINGS.set(self).is_ok();
+    }
+
+    /// The applied settings, the defaults if none were applied.
+    pub fn current() -> &'static Settings {
+        return SETTINGS.get_or_init(Settings::default);
+    }
+}
@@ -1,10 +1,16 @@
 use std::path::Path;
+use serde::Serialize;
+use crate::config::GAME_ID;
+use crate::error::InstallerError;
+use crate::settings::Settings;
+#[cfg(target_os = "windows")]
 use winreg::enums::*;
+#[cfg(target_os = "windows")]
 use winreg::RegKey;
 
-pub(crate) struct Steam;
+pub struct Steam;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SteamGame {
     pub id: String,
     pub name: String,
@@ -43,14 +49,98 @@ impl Steam {
         return Some(library_folders);
     }
 
+    // looks through every steam library for the game with the given app id
+    pub fn find_game(id: &str) -> Result<SteamGame, InstallerError> {
+        // the game path setting skips steam entirely
+        if let (Some(path), true) = (&Settings::current().game_path, id == GAME_ID) {
+            return Steam::game_at(path);
+        }
+
+        let steam_path = match Steam::get_steam_path() {
+            Some(path) => path,
+            None => return Err(InstallerError::SteamError("Steam not found".to_string())),
+        };
+        let games = match Steam::iterate_games(&steam_path) {
+            Some(games) => games,
+            None => return Err(InstallerError::SteamError(format!("No Steam libraries found in {}", steam_path))),
+        };
+
+        return match games.into_iter().find(|game| game.id == id) {
+            Some(game) => Ok(game),
+            None => Err(InstallerError::SteamError(format!("Game {} is not installed", id))),
+        };
+    }
+
+    /// Soulstone Survivors in a folder that was given instead of found.
+    pub fn game_at(path: &str) -> Result<SteamGame, InstallerError> {
+        if !Path::new(path).is_dir() {
+            return Err(InstallerError::SteamError(format!("Game folder {} does not exist", path)));
+        }
+
+        return Ok(SteamGame {
+            id: GAME_ID.to_string(),
+            name: "Soulstone Survivors".to_string(),
+            path: path.to_string(),
+        });
+    }
+
+    /// A value from the app manifest of the game, such as `buildid`. The manifest sits in `steamapps`,
+    /// two folders up from `steamapps/common/<game>`.
+    pub fn manifest_value(game_path: &str, id: &str, key: &str) -> Option<String> {
+        let steamapps = Path::new(game_path).parent()?.parent()?;
+        let manifest = std::fs::read_to_string(steamapps.join(format!("appmanifest_{}.acf", id))).ok()?;
+        let quoted_key = format!("\"{}\"", key);
+
+        return manifest
+            .lines()
+            .find(|x| x.trim().starts_with(&quoted_key))
+            .and_then(|x| x.split('"').nth(3))
+            .map(|x| x.to_string());
+    }
+
+    /// Launch options of a game from `userdata/<user>/config/localconfig.vdf`, the first user that set any wins.
+    pub fn launch_options(path: &str, id: &str) -> Option<String> {
+        let userdata = std::fs::read_dir(Path::new(path).join("userdata")).ok()?;
+        for user in userdata.filter_map(|x| x.ok()) {
+            let config = std::fs::read_to_string(user.path().join("config").join("localconfig.vdf"));
+            if let Some(options) = config.ok().and_then(|x| Steam::find_launch_options(&x, id)) {
+                return Some(options);
+            }
+        }
+
+        return None;
+    }
+
+    // the `"LaunchOptions"` directly inside a `"<id>"` block
+    fn find_launch_options(config: &str, id: &str) -> Option<String> {
+        let quoted_id = format!("\"{}\"", id);
+        let mut depth = None;
+        for line in config.lines().map(|x| x.trim()) {
+            match depth {
+                None if line == quoted_id => depth = Some(0),
+                None => {}
+                Some(current) => {
+                    if line == "{" {
+                        depth = Some(current + 1);
+                    } else if line == "}" {
+                        // other blocks can have the same key, keep looking
+                        depth = if current <= 1 { None } else { Some(current - 1) };
+                    } else if current == 1 && line.starts_with("\"LaunchOptions\"") {
+                        let value = line["\"LaunchOptions\"".len()..].trim().trim_matches('"');
+                        return Some(value.replace("\\\"", "\"").replace("\\\\", "\\"));
+                    }
+                }
+            }
+        }
+
+        return None;
+    }
+
     pub fn iterate_games(path: &str) -> Option<Vec<SteamGame>> {
         let mut games = Vec::new();
-        let folders = Steam::get_library_folders(path);
-        if folders.is_none() {
-            return None;
-        }
+        let folders = Steam::get_library_folders(path)?;
 
-        for folder in folders.unwrap() {
+        for folder in folders {
             let folder_path = Path::new(&folder).join("steamapps");
             let apps = folder_path.read_dir();
             if apps.is_err() {
@@ -65,7 +155,11 @@ impl Steam {
                 if app_path.is_dir() {
                     continue;
                 }
-                let app_name = app_path.file_name().unwrap().to_str().unwrap();
+                // names that are not valid unicode are never app manifests
+                let app_name = match app_path.file_name().and_then(|x| x.to_str()) {
+                    Some(name) => name,
+                    None => continue,
+                };
                 if !app_name.starts_with("appmanifest_") {
                     continue;
                 }
@@ -97,7 +191,7 @@ impl Steam {
                     continue;
                 }
                 let app_path = folder_path.join("common").join(app_path);
-                let app_path = app_path.to_str().unwrap().to_string();
+                let app_path = app_path.to_string_lossy().to_string();
                 let game = SteamGame {
                     id: app_id,
                     name: app_name,
@@ -142,11 +236,7 @@ impl Steam {
     #[cfg(target_os = "linux")]
     pub fn get_steam_path() -> Option<String> {
         // get home directory
-        let home_dir = dirs::home_dir();
-        if home_dir.is_none() {
-            return None;
-        }
-        let home_dir = home_dir.unwrap();
+        let home_dir = dirs::home_dir()?;
 
         // get steam path
         let steam_path = home_dir.join(".steam/steam");
@@ -154,7 +244,7 @@ impl Steam {
             return None;
         }
 
-        return Some(steam_path.to_str().unwrap().to_string());
+        return Some(steam_path.to_string_lossy().to_string());
     }
 
 
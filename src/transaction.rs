@@ -0,0 +1,278 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// used to give every transaction of this process its own work folder
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Stages files in a work folder and moves them into `target` in one go.
+/// Files that get replaced are backed up first, so a failed commit (or a
+/// transaction that is dropped without committing) leaves `target` untouched.
+pub struct Transaction {
+    target: PathBuf,
+    temp: PathBuf,
+    work: PathBuf,
+    // files that did not exist before the commit
+    created: Vec<PathBuf>,
+    // folders that did not exist before the commit
+    created_dirs: Vec<PathBuf>,
+    // files that were moved to the backup folder
+    replaced: Vec<PathBuf>,
+    // files that should be deleted from the target on commit
+    removals: Vec<PathBuf>,
+    committed: bool,
+}
+
+impl Transaction {
+    pub fn new(target: &Path, temp: &Path) -> Result<Self, std::io::Error> {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let work = temp.join(format!("transaction-{}-{}", std::process::id(), id));
+
+        std::fs::create_dir_all(work.join("staging"))?;
+        std::fs::create_dir_all(work.join("backup"))?;
+
+        return Ok(Self {
+            target: target.to_path_buf(),
+            temp: temp.to_path_buf(),
+            work,
+            created: Vec::new(),
+            created_dirs: Vec::new(),
+            replaced: Vec::new(),
+            removals: Vec::new(),
+            committed: false,
+        });
+    }
+
+    // everything in here ends up in the target folder on commit
+    pub fn staging_path(&self) -> PathBuf {
+        return self.work.join("staging");
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        return self.work.join("backup");
+    }
+
+    // schedules a file relative to the target folder for deletion, it is backed up like a replaced file
+    pub fn remove(&mut self, relative: &Path) {
+        self.removals.push(relative.to_path_buf());
+    }
+
+    // stages a copy of `source` so it ends up at `relative` in the target folder
+    pub fn stage_file(&self, source: &Path, relative: &Path) -> Result<(), std::io::Error> {
+        let staged = self.staging_path().join(relative);
+        if let Some(parent) = staged.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(source, staged)?;
+
+        return Ok(());
+    }
+
+    // moves a file inside the target folder on commit
+    pub fn move_file(&mut self, from: &Path, to: &Path) -> Result<(), std::io::Error> {
+        self.stage_file(&self.target.join(from), to)?;
+        self.remove(from);
+
+        return Ok(());
+    }
+
+    /// Moves every staged file into the target folder and returns their new paths.
+    /// If any file fails to move, everything done so far is rolled back.
+    pub fn commit(mut self) -> Result<Vec<PathBuf>, std::io::Error> {
+        let mut files = Vec::new();
+        Transaction::collect_files(&self.staging_path(), Path::new(""), &mut files)?;
+
+        for relative in &files {
+            if let Err(err) = self.commit_file(relative) {
+                self.rollback();
+                return Err(err);
+            }
+        }
+        let removals = std::mem::take(&mut self.removals);
+        for relative in &removals {
+            if let Err(err) = self.remove_file(relative) {
+                self.rollback();
+                return Err(err);
+            }
+        }
+
+        self.committed = true;
+
+        // folders that only held removed files are not needed anymore, nothing is rolled back from here on.
+        // the first two levels like `BepInEx/plugins` are kept, the loader expects them
+        for relative in &removals {
+            let mut parent = relative.parent();
+            while let Some(dir) = parent.filter(|x| x.components().count() > 2) {
+                if std::fs::remove_dir(self.target.join(dir)).is_err() {
+                    break;
+                }
+                parent = dir.parent();
+            }
+        }
+
+        return Ok(files.iter().map(|relative| self.target.join(relative)).collect());
+    }
+
+    fn commit_file(&mut self, relative: &Path) -> Result<(), std::io::Error> {
+        let source = self.staging_path().join(relative);
+        let destination = self.target.join(relative);
+
+        // create missing parent folders and remember them for the rollback
+        if let Some(parent) = destination.parent() {
+            let mut missing = Vec::new();
+            let mut current = parent;
+            while !current.exists() {
+                missing.push(current.to_path_buf());
+                match current.parent() {
+                    Some(next) => current = next,
+                    None => break,
+                }
+            }
+            for dir in missing.into_iter().rev() {
+                std::fs::create_dir(&dir)?;
+                self.created_dirs.push(dir);
+            }
+        }
+
+        // back up the file that is about to be replaced
+        if destination.exists() {
+            self.backup_file(relative)?;
+        } else {
+            self.created.push(relative.to_path_buf());
+        }
+
+        std::fs::rename(&source, &destination)?;
+
+        return Ok(());
+    }
+
+    fn remove_file(&mut self, relative: &Path) -> Result<(), std::io::Error> {
+        if !self.target.join(relative).exists() {
+            return Ok(());
+        }
+
+        return self.backup_file(relative);
+    }
+
+    // moves a file out of the target folder so the rollback can put it back
+    fn backup_file(&mut self, relative: &Path) -> Result<(), std::io::Error> {
+        // the file was put there by this transaction, the backup already holds what was there before
+        if self.replaced.iter().chain(&self.created).any(|x| x == relative) {
+            return std::fs::remove_file(self.target.join(relative));
+        }
+
+        let backup = self.backup_path().join(relative);
+        if let Some(parent) = backup.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(self.target.join(relative), &backup)?;
+        self.replaced.push(relative.to_path_buf());
+
+        return Ok(());
+    }
+
+    // undo the commit in reverse order, errors are ignored so we restore as much as possible
+    fn rollback(&mut self) {
+        for relative in self.created.drain(..).rev() {
+            let _ = std::fs::remove_file(self.target.join(relative));
+        }
+        for relative in self.replaced.drain(..).rev() {
+            let backup = self.work.join("backup").join(&relative);
+            let _ = std::fs::rename(backup, self.target.join(relative));
+        }
+        // only removes folders that are empty again
+        for dir in self.created_dirs.drain(..).rev() {
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+
+    // collects files below root/relative as paths relative to root, relative may also be a single file
+    pub(crate) fn collect_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+        let path = root.join(relative);
+        if path.is_file() {
+            files.push(relative.to_path_buf());
+            return Ok(());
+        }
+        if !path.is_dir() {
+            return Ok(());
+        }
+
+        for entry in std::fs::read_dir(&path)? {
+            let entry = entry?;
+            Transaction::collect_files(root, &relative.join(entry.file_name()), files)?;
+        }
+
+        return Ok(());
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.rollback();
+        }
+
+        // remove the work folder and the temp folder once nothing else uses it
+        let _ = std::fs::remove_dir_all(&self.work);
+        let _ = std::fs::remove_dir(&self.temp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollback_restores_the_previous_files() {
+        let root = std::env::temp_dir().join(format!("bepinex_installer-transaction-{}", std::process::id()));
+        let (target, temp) = (root.join("game"), root.join("temp"));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("Replaced.dll"), "old").unwrap();
+        std::fs::write(target.join("Removed.dll"), "kept").unwrap();
+        // a file where the commit needs a folder makes it fail
+        std::fs::write(target.join("blocked"), "file").unwrap();
+
+        let mut transaction = Transaction::new(&target, &temp).unwrap();
+        std::fs::write(transaction.staging_path().join("Replaced.dll"), "new").unwrap();
+        std::fs::write(transaction.staging_path().join("Created.dll"), "new").unwrap();
+        std::fs::create_dir_all(transaction.staging_path().join("blocked")).unwrap();
+        std::fs::write(transaction.staging_path().join("blocked").join("Mod.dll"), "new").unwrap();
+        transaction.remove(Path::new("Removed.dll"));
+        assert!(transaction.commit().is_err());
+
+        assert_eq!(std::fs::read_to_string(target.join("Replaced.dll")).unwrap(), "old");
+        assert_eq!(std::fs::read_to_string(target.join("Removed.dll")).unwrap(), "kept");
+        assert_eq!(std::fs::read_to_string(target.join("blocked")).unwrap(), "file");
+        assert!(!target.join("Created.dll").exists());
+        assert!(!temp.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn rollback_restores_a_file_that_was_staged_and_removed() {
+        let root = std::env::temp_dir().join(format!("bepinex_installer-transaction-twice-{}", std::process::id()));
+        let (target, temp) = (root.join("game"), root.join("temp"));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("Mod.dll"), "old").unwrap();
+
+        let mut transaction = Transaction::new(&target, &temp).unwrap();
+        std::fs::write(transaction.staging_path().join("Mod.dll"), "new").unwrap();
+        transaction.remove(Path::new("Mod.dll"));
+        assert!(transaction.commit().is_ok());
+        assert!(!target.join("Mod.dll").exists());
+
+        // the same again, rolled back this time
+        std::fs::write(target.join("Mod.dll"), "old").unwrap();
+        let mut transaction = Transaction::new(&target, &temp).unwrap();
+        std::fs::write(transaction.staging_path().join("Mod.dll"), "new").unwrap();
+        transaction.remove(Path::new("Mod.dll"));
+        transaction.commit_file(Path::new("Mod.dll")).unwrap();
+        transaction.remove_file(Path::new("Mod.dll")).unwrap();
+        transaction.rollback();
+        drop(transaction);
+
+        assert_eq!(std::fs::read_to_string(target.join("Mod.dll")).unwrap(), "old");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
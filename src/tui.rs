@@ -0,0 +1,405 @@
+use std::collections::VecDeque;
+use std::io::{stdout, Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use crossterm::cursor::MoveTo;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use bepinex_installer::config::TITLE;
+use bepinex_installer::{Plugin, Records, Settings};
+
+// rows used by everything except the plugin list
+const HEADER_ROWS: u16 = 3;
+const FOOTER_ROWS: u16 = 7;
+// release lookups running at the same time
+const LOOKUP_JOBS: usize = 4;
+
+/// Full screen plugin picker shown instead of the Y/N prompts.
+pub struct PluginBrowser<'a> {
+    plugins: &'a [Plugin],
+    records: &'a Records,
+    selected: Vec<bool>,
+    cursor: usize,
+    scroll: usize,
+    search: String,
+    searching: bool,
+    confirming: bool,
+    message: Option<String>,
+}
+
+// latest release lookups, only for the rows that are drawn and a few at a time.
+// the releases are cached in the plugins, failed lookups are not tried again
+struct Lookups {
+    requested: Vec<AtomicBool>,
+    // set once the latest release of a plugin was looked up, even if that failed
+    fetched: Vec<AtomicBool>,
+    queue: Mutex<VecDeque<usize>>,
+    done: AtomicBool,
+}
+
+impl Lookups {
+    fn new(count: usize) -> Self {
+        Self {
+            requested: (0..count).map(|_| AtomicBool::new(false)).collect(),
+            fetched: (0..count).map(|_| AtomicBool::new(false)).collect(),
+            queue: Mutex::new(VecDeque::new()),
+            done: AtomicBool::new(false),
+        }
+    }
+
+    fn request(&self, index: usize) {
+        if !self.requested[index].swap(true, Ordering::SeqCst) {
+            self.queue.lock().unwrap().push_back(index);
+        }
+    }
+
+    fn work(&self, plugins: &[Plugin]) {
+        while !self.done.load(Ordering::SeqCst) {
+            // the rows asked for last are the ones on screen right now
+            let next = self.queue.lock().unwrap().pop_back();
+            match next {
+                Some(index) => {
+                    let _ = plugins[index].latest_release();
+                    self.fetched[index].store(true, Ordering::SeqCst);
+                }
+                None => std::thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+}
+
+enum Action {
+    Continue,
+    Cancel,
+    Install,
+}
+
+impl<'a> PluginBrowser<'a> {
+    pub fn new(plugins: &'a [Plugin], records: &'a Records) -> Self {
+        let defaults = &Settings::current().default_plugins;
+        Self {
+            plugins,
+            records,
+            selected: plugins.iter().map(|x| defaults.iter().any(|y| y.eq_ignore_ascii_case(&x.name))).collect(),
+            cursor: 0,
+            scroll: 0,
+            search: String::new(),
+            searching: false,
+            confirming: false,
+            message: None,
+        }
+    }
+
+    /// Runs the browser until the user confirms or quits.
+    /// Returns the indices of the selected plugins, `None` if the user quit.
+    pub fn run(mut self) -> Result<Option<Vec<usize>>, std::io::Error> {
+        let mut out = stdout();
+        crossterm::terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen)?;
+
+        let plugins = self.plugins;
+        let lookups = Lookups::new(plugins.len());
+        let result = std::thread::scope(|scope| {
+            // look up the latest versions in the background, the list fills in as they arrive
+            for _ in 0..LOOKUP_JOBS.min(plugins.len()) {
+                scope.spawn(|| lookups.work(plugins));
+            }
+
+            let result = self.event_loop(&mut out, &lookups);
+
+            // leave the alternate screen before waiting for lookups that are still running
+            let _ = execute!(out, LeaveAlternateScreen);
+            let _ = crossterm::terminal::disable_raw_mode();
+            lookups.done.store(true, Ordering::SeqCst);
+            result
+        });
+
+        return result;
+    }
+
+    fn event_loop(&mut self, out: &mut Stdout, lookups: &Lookups) -> Result<Option<Vec<usize>>, std::io::Error> {
+        loop {
+            self.draw(out, lookups)?;
+
+            // redraw every now and then so new versions show up
+            if !crossterm::event::poll(Duration::from_millis(250))? {
+                continue;
+            }
+            let key = match crossterm::event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+
+            let action = if self.confirming {
+                self.confirm_key(key)
+            } else if self.searching {
+                self.search_key(key)
+            } else {
+                self.list_key(key)
+            };
+
+            match action {
+                Action::Continue => {}
+                Action::Cancel => return Ok(None),
+                Action::Install => {
+                    let selected = (0..self.plugins.len()).filter(|&x| self.selected[x]).collect();
+                    return Ok(Some(selected));
+                }
+            }
+        }
+    }
+
+    fn list_key(&mut self, key: KeyEvent) -> Action {
+        let visible = self.visible();
+        self.message = None;
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Action::Cancel,
+            KeyCode::Esc | KeyCode::Char('q') => return Action::Cancel,
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') if self.cursor + 1 < visible.len() => self.cursor += 1,
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(10),
+            KeyCode::PageDown => self.cursor = (self.cursor + 10).min(visible.len().saturating_sub(1)),
+            KeyCode::Char(' ') => {
+                if let Some(&index) = visible.get(self.cursor) {
+                    self.selected[index] = !self.selected[index];
+                }
+            }
+            KeyCode::Char('a') => {
+                // select all visible plugins, or clear them if they are all selected already
+                let all = visible.iter().all(|&x| self.selected[x]);
+                for index in visible {
+                    self.selected[index] = !all;
+                }
+            }
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Enter => {
+                if self.selected.iter().any(|&x| x) {
+                    self.confirming = true;
+                } else {
+                    self.message = Some("Select at least one plugin with space first.".to_string());
+                }
+            }
+            _ => {}
+        }
+
+        return Action::Continue;
+    }
+
+    fn search_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Action::Cancel,
+            KeyCode::Esc => {
+                self.search.clear();
+                self.searching = false;
+            }
+            KeyCode::Enter | KeyCode::Down => self.searching = false,
+            KeyCode::Backspace => {
+                self.search.pop();
+            }
+            KeyCode::Char(c) => self.search.push(c),
+            _ => {}
+        }
+
+        // the filtered list changed, start at the top again
+        self.cursor = 0;
+        self.scroll = 0;
+
+        return Action::Continue;
+    }
+
+    fn confirm_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Action::Cancel,
+            KeyCode::Char('y') | KeyCode::Enter => return Action::Install,
+            KeyCode::Char('n') | KeyCode::Esc | KeyCode::Backspace => self.confirming = false,
+            _ => {}
+        }
+
+        return Action::Continue;
+    }
+
+    // indices of the plugins matching the search
+    fn visible(&self) -> Vec<usize> {
+        let search = self.search.to_lowercase();
+        return self.plugins
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| search.is_empty() || x.name.to_lowercase().contains(&search) || x.description.to_lowercase().contains(&search))
+            .map(|(index, _)| index)
+            .collect();
+    }
+
+    fn draw(&mut self, out: &mut Stdout, lookups: &Lookups) -> Result<(), std::io::Error> {
+        let (width, height) = crossterm::terminal::size()?;
+        let width = width as usize;
+        queue!(out, Clear(ClearType::All))?;
+
+        if self.confirming {
+            self.draw_confirm(out, width, height, lookups)?;
+        } else {
+            self.draw_list(out, width, height, lookups)?;
+        }
+
+        return out.flush();
+    }
+
+    fn draw_list(&mut self, out: &mut Stdout, width: usize, height: u16, lookups: &Lookups) -> Result<(), std::io::Error> {
+        let visible = self.visible();
+        let selected = self.selected.iter().filter(|&&x| x).count();
+
+        // header
+        queue!(out, MoveTo(0, 0), SetAttribute(Attribute::Bold), Print(fit(&format!("{} - Plugins ({} selected)", TITLE, selected), width)), SetAttribute(Attribute::Reset))?;
+        let search = if self.searching {
+            format!("Search: {}_", self.search)
+        } else if !self.search.is_empty() {
+            format!("Search: {}", self.search)
+        } else {
+            "Press / to search".to_string()
+        };
+        queue!(out, MoveTo(0, 1), SetForegroundColor(Color::DarkGrey), Print(fit(&search, width)), ResetColor)?;
+
+        // keep the cursor inside the scrolled area
+        let rows = height.saturating_sub(HEADER_ROWS + FOOTER_ROWS).max(1) as usize;
+        if self.cursor >= visible.len() {
+            self.cursor = visible.len().saturating_sub(1);
+        }
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        }
+        if self.cursor >= self.scroll + rows {
+            self.scroll = self.cursor + 1 - rows;
+        }
+
+        let name_width = self.plugins.iter().map(|x| x.name.len()).max().unwrap_or(0);
+        if visible.is_empty() {
+            queue!(out, MoveTo(0, HEADER_ROWS), Print("No plugins match the search."))?;
+        }
+        for (row, &index) in visible.iter().enumerate().skip(self.scroll).take(rows) {
+            let plugin = &self.plugins[index];
+            let pointer = if row == self.cursor { ">" } else { " " };
+            let checkbox = if self.selected[index] { "[x]" } else { "[ ]" };
+            let version = self.version(index, lookups);
+            let license = plugin.license.clone().unwrap_or("-".to_string());
+            let line = format!(
+                "{} {} {:name_width$}  {:12}  \u{2605} {:<5}  {:8}",
+                pointer, checkbox, plugin.name, version, plugin.stars, license, name_width = name_width,
+            );
+
+            let y = HEADER_ROWS + (row - self.scroll) as u16;
+            if row == self.cursor {
+                queue!(out, MoveTo(0, y), SetAttribute(Attribute::Reverse), Print(fit(&line, width)), SetAttribute(Attribute::Reset))?;
+            } else {
+                queue!(out, MoveTo(0, y), Print(fit(&line, width)))?;
+            }
+
+            // installed state goes after the line in its own colour
+            let (state, color) = self.installed_state(index);
+            let x = (line.chars().count() + 2) as u16;
+            if !state.is_empty() && (x as usize) < width {
+                queue!(out, MoveTo(x, y), SetForegroundColor(color), Print(fit(&state, width - x as usize)), ResetColor)?;
+            }
+        }
+
+        // details of the plugin under the cursor
+        let footer = height.saturating_sub(FOOTER_ROWS);
+        queue!(out, MoveTo(0, footer), SetForegroundColor(Color::DarkGrey), Print("\u{2500}".repeat(width)), ResetColor)?;
+        if let Some(&index) = visible.get(self.cursor) {
+            let plugin = &self.plugins[index];
+            queue!(out, MoveTo(0, footer + 1), SetAttribute(Attribute::Bold), Print(fit(&plugin.name, width)), SetAttribute(Attribute::Reset))?;
+            for (line, text) in wrap(&plugin.description, width).iter().take(3).enumerate() {
+                queue!(out, MoveTo(0, footer + 2 + line as u16), Print(text))?;
+            }
+            queue!(out, MoveTo(0, footer + 5), SetForegroundColor(Color::DarkGrey), Print(fit(&plugin.repo, width)), ResetColor)?;
+        }
+
+        let help = match &self.message {
+            Some(message) => message.clone(),
+            None => "\u{2191}/\u{2193} move  space select  a all  / search  enter install  q quit".to_string(),
+        };
+        queue!(out, MoveTo(0, footer + 6), SetForegroundColor(Color::Yellow), Print(fit(&help, width)), ResetColor)?;
+
+        return Ok(());
+    }
+
+    fn draw_confirm(&self, out: &mut Stdout, width: usize, height: u16, lookups: &Lookups) -> Result<(), std::io::Error> {
+        let selected = (0..self.plugins.len()).filter(|&x| self.selected[x]).collect::<Vec<_>>();
+
+        // the title, a blank line, the plugins, a blank line and the help, the rest is summed up
+        let rows = height.saturating_sub(4).max(1) as usize;
+        let shown = if selected.len() > rows { rows - 1 } else { selected.len() };
+
+        queue!(out, MoveTo(0, 0), SetAttribute(Attribute::Bold), Print(fit(&format!("Install {} plugins?", selected.len()), width)), SetAttribute(Attribute::Reset))?;
+        for (row, &index) in selected.iter().take(shown).enumerate() {
+            let plugin = &self.plugins[index];
+            let (state, _) = self.installed_state(index);
+            let line = format!("  {} {}  {}", plugin.name, self.version(index, lookups), state);
+            queue!(out, MoveTo(0, 2 + row as u16), Print(fit(&line, width)))?;
+        }
+        let mut end = 2 + shown as u16;
+        if shown < selected.len() {
+            queue!(out, MoveTo(0, end), SetForegroundColor(Color::DarkGrey), Print(fit(&format!("  ... and {} more", selected.len() - shown), width)), ResetColor)?;
+            end += 1;
+        }
+        let help = "[Y] Yes, install them  [N] No, go back";
+        queue!(out, MoveTo(0, end + 1), SetForegroundColor(Color::Yellow), Print(fit(help, width)), ResetColor)?;
+
+        return Ok(());
+    }
+
+    // asks for the release of rows that are drawn without one yet
+    fn version(&self, index: usize, lookups: &Lookups) -> String {
+        if let Some(release) = self.plugins[index].release() {
+            return release.tag_name.clone();
+        }
+        if lookups.fetched[index].load(Ordering::SeqCst) {
+            return "unknown".to_string();
+        }
+        lookups.request(index);
+
+        return "loading...".to_string();
+    }
+
+    fn installed_state(&self, index: usize) -> (String, Color) {
+        let plugin = &self.plugins[index];
+        let record = match self.records.find(&plugin.name) {
+            Some(record) => record,
+            None => return (String::new(), Color::Reset),
+        };
+        if record.disabled {
+            return (format!("disabled {}", record.version), Color::DarkGrey);
+        }
+
+        return match plugin.release() {
+            Some(release) if release.tag_name != record.version => (format!("update available ({} installed)", record.version), Color::Yellow),
+            _ => (format!("installed {}", record.version), Color::Green),
+        };
+    }
+}
+
+// cuts text to the terminal width
+fn fit(text: &str, width: usize) -> String {
+    return text.chars().take(width).collect();
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    return lines;
+}
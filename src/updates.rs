@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::backup::Backup;
+use crate::config::GAME_ID;
+use crate::error::InstallerError;
+use crate::loader::LoaderKind;
+use crate::plugins::Plugins;
+use crate::records::Records;
+use crate::steam::Steam;
+
+/// Notices when Steam updated the game after the mods were set up. Interop assemblies generated from the
+/// old build are stale then, and plugins built against it often break.
+pub struct GameUpdates {
+    path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameUpdate {
+    pub previous_build: String,
+    pub build: String,
+    // unix time Steam finished the update, from the app manifest
+    pub updated_at: Option<u64>,
+    // enabled plugins, they were installed for the previous build
+    pub affected: Vec<String>,
+    // the interop folder of the loader still holds the assemblies generated from the previous build
+    pub interop: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginUpdate {
+    pub name: String,
+    pub installed: String,
+    pub latest: String,
+    pub published_at: String,
+    // released after the game update, so it was likely built for the new build
+    pub compatible: bool,
+}
+
+impl GameUpdates {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    fn interop_path(&self) -> PathBuf {
+        return Path::new(&self.path).join(LoaderKind::active().interop_path());
+    }
+
+    /// The build of the game Steam has installed right now.
+    pub fn current_build(&self) -> Option<String> {
+        return Steam::manifest_value(&self.path, GAME_ID, "buildid");
+    }
+
+    /// Compares the installed build with the one the mods were set up for.
+    /// Setups from before builds were recorded get the current build recorded instead.
+    pub fn check(&self) -> Result<Option<GameUpdate>, InstallerError> {
+        // a game without the installer's records was never modded by it
+        if !Path::new(&self.path).join("BepInEx").join("installer.json").exists() {
+            return Ok(None);
+        }
+        let build = match self.current_build() {
+            Some(build) => build,
+            None => return Ok(None),
+        };
+        let records = Records::load(&self.path);
+        let previous_build = match &records.game_build {
+            Some(previous) if *previous != build => previous.clone(),
+            Some(_) => return Ok(None),
+            None => {
+                self.acknowledge()?;
+                return Ok(None);
+            }
+        };
+
+        let interop = std::fs::read_dir(self.interop_path()).map(|mut x| x.next().is_some()).unwrap_or(false);
+        return Ok(Some(GameUpdate {
+            previous_build,
+            build,
+            updated_at: Steam::manifest_value(&self.path, GAME_ID, "LastUpdated").and_then(|x| x.parse().ok()),
+            affected: records.plugins.iter().filter(|x| !x.disabled).map(|x| x.name.clone()).collect(),
+            interop,
+        }));
+    }
+
+    /// Newer releases of the affected plugins, on the configured channel.
+    pub fn plugin_updates(&self, update: &GameUpdate) -> Result<Vec<PluginUpdate>, InstallerError> {
+        let records = Records::load(&self.path);
+        let plugins = Plugins::get_plugins()?;
+        // github dates are iso 8601 in utc, they compare as text
+        let updated_at = update.updated_at.map(|x| {
+            let (year, month, day, time) = Backup::civil_date(x);
+            format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+        });
+
+        let mut updates = Vec::new();
+        for name in &update.affected {
+            let (record, plugin) = match (records.find(name), plugins.iter().find(|x| x.name.eq_ignore_ascii_case(name))) {
+                (Some(record), Some(plugin)) => (record, plugin),
+                _ => continue,
+            };
+            // one plugin without releases should not hide the others
+            let release = match plugin.latest_release() {
+                Ok(release) => release,
+                Err(_) => continue,
+            };
+            if release.tag_name == record.version {
+                continue;
+            }
+            updates.push(PluginUpdate {
+                name: record.name.clone(),
+                installed: record.version.clone(),
+                latest: release.tag_name.clone(),
+                published_at: release.published_at.clone(),
+                compatible: updated_at.as_ref().map(|x| release.published_at >= *x).unwrap_or(false),
+            });
+        }
+
+        return Ok(updates);
+    }
+
+    /// Deletes the interop assemblies, the loader generates them from the new build on the next launch.
+    pub fn clear_interop(&self) -> Result<(), InstallerError> {
+        let interop_path = self.interop_path();
+        if interop_path.exists() {
+            std::fs::remove_dir_all(&interop_path).map_err(InstallerError::io(&interop_path))?;
+        }
+
+        return Ok(());
+    }
+
+    /// Records the current build as the one the mods are set up for, so it is not reported again.
+    pub fn acknowledge(&self) -> Result<(), InstallerError> {
+        let mut records = Records::load(&self.path);
+        records.game_build = self.current_build();
+        return records.save(&self.path);
+    }
+}